@@ -3,6 +3,7 @@ extern crate openssl;
 use std::io;
 use std::fmt;
 use std::error;
+use std::net::IpAddr;
 use self::openssl::pkcs12;
 use self::openssl::error::ErrorStack;
 use self::openssl::ssl::{self, SslMethod, SslConnectorBuilder, SslConnector, SslAcceptorBuilder,
@@ -145,11 +146,15 @@ impl<S> From<ErrorStack> for HandshakeError<S> {
     }
 }
 
-pub struct TlsConnectorBuilder(SslConnectorBuilder);
+pub struct TlsConnectorBuilder {
+    connector: SslConnectorBuilder,
+    sni_name: Option<String>,
+    accept_invalid_hostnames: bool,
+}
 
 impl TlsConnectorBuilder {
     pub fn identity(&mut self, pkcs12: Pkcs12) -> Result<(), Error> {
-        let ctx = self.0.builder_mut();
+        let ctx = self.connector.builder_mut();
         // FIXME clear chain certs to clean up if called multiple times
         try!(ctx.set_certificate(&pkcs12.0.cert));
         try!(ctx.set_private_key(&pkcs12.0.pkey));
@@ -161,34 +166,68 @@ impl TlsConnectorBuilder {
     }
 
     pub fn add_root_certificate(&mut self, cert: Certificate) -> Result<(), Error> {
-        try!(self.0.builder_mut().cert_store_mut().add_cert(cert.0));
+        try!(self.connector.builder_mut().cert_store_mut().add_cert(cert.0));
         Ok(())
     }
 
     pub fn supported_protocols(&mut self, protocols: &[Protocol]) -> Result<(), Error> {
-        supported_protocols(protocols, self.0.builder_mut());
+        supported_protocols(protocols, self.connector.builder_mut());
+        Ok(())
+    }
+
+    pub fn sni_name(&mut self, name: &str) -> Result<(), Error> {
+        self.sni_name = Some(name.to_owned());
+        Ok(())
+    }
+
+    pub fn danger_accept_invalid_hostnames(&mut self, accept_invalid_hostnames: bool) -> Result<(), Error> {
+        self.accept_invalid_hostnames = accept_invalid_hostnames;
         Ok(())
     }
 
     pub fn build(self) -> Result<TlsConnector, Error> {
-        Ok(TlsConnector(self.0.build()))
+        Ok(TlsConnector {
+            connector: self.connector.build(),
+            sni_name: self.sni_name,
+            accept_invalid_hostnames: self.accept_invalid_hostnames,
+        })
     }
 }
 
 #[derive(Clone)]
-pub struct TlsConnector(SslConnector);
+pub struct TlsConnector {
+    connector: SslConnector,
+    sni_name: Option<String>,
+    accept_invalid_hostnames: bool,
+}
 
 impl TlsConnector {
     pub fn builder() -> Result<TlsConnectorBuilder, Error> {
         let builder = try!(SslConnectorBuilder::new(SslMethod::tls()));
-        Ok(TlsConnectorBuilder(builder))
+        Ok(TlsConnectorBuilder {
+            connector: builder,
+            sni_name: None,
+            accept_invalid_hostnames: false,
+        })
     }
 
     pub fn connect<S>(&self, domain: &str, stream: S) -> Result<TlsStream<S>, HandshakeError<S>>
     where
         S: io::Read + io::Write,
     {
-        let s = try!(self.0.connect(domain, stream));
+        let domain = self.sni_name.as_ref().map(|s| &s[..]).unwrap_or(domain);
+
+        if !self.accept_invalid_hostnames {
+            let s = try!(self.connector.connect(domain, stream));
+            return Ok(TlsStream(s));
+        }
+
+        // Still send SNI, IP addresses aren't allowed in it
+        let mut config = try!(self.connector.configure());
+        if domain.parse::<IpAddr>().is_err() {
+            try!(config.set_hostname(domain));
+        }
+        let s = try!(config.danger_connect_without_providing_domain_for_certificate_verification_and_server_name_indication(stream));
         Ok(TlsStream(s))
     }
 
@@ -196,7 +235,7 @@ impl TlsConnector {
     where
         S: io::Read + io::Write,
     {
-        let s = try!(self.0.danger_connect_without_providing_domain_for_certificate_verification_and_server_name_indication(stream));
+        let s = try!(self.connector.danger_connect_without_providing_domain_for_certificate_verification_and_server_name_indication(stream));
         Ok(TlsStream(s))
     }
 }
@@ -215,15 +254,19 @@ pub trait TlsConnectorBuilderExt {
 
 impl TlsConnectorBuilderExt for ::TlsConnectorBuilder {
     fn from_openssl(builder: SslConnectorBuilder) -> ::TlsConnectorBuilder {
-        ::TlsConnectorBuilder(TlsConnectorBuilder(builder))
+        ::TlsConnectorBuilder(TlsConnectorBuilder {
+            connector: builder,
+            sni_name: None,
+            accept_invalid_hostnames: false,
+        })
     }
 
     fn builder(&self) -> &SslConnectorBuilder {
-        &(self.0).0
+        &(self.0).connector
     }
 
     fn builder_mut(&mut self) -> &mut SslConnectorBuilder {
-        &mut (self.0).0
+        &mut (self.0).connector
     }
 }
 
@@ -302,6 +345,14 @@ impl<S: io::Read + io::Write> TlsStream<S> {
         Ok(self.0.ssl().pending())
     }
 
+    pub fn peer_certificate_chain(&self) -> Result<Vec<Vec<u8>>, Error> {
+        // On the client side OpenSSL's chain starts with the peer's own certificate
+        match self.0.ssl().peer_cert_chain() {
+            Some(chain) => Ok(try!(chain.iter().map(|cert| cert.to_der()).collect())),
+            None => Ok(vec![]),
+        }
+    }
+
     pub fn shutdown(&mut self) -> io::Result<()> {
         match self.0.shutdown() {
             Ok(_) |
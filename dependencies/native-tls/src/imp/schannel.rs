@@ -9,6 +9,9 @@ use self::schannel::cert_context::CertContext;
 use self::schannel::schannel_cred::{Direction, SchannelCred, Protocol};
 use self::schannel::tls_stream;
 
+// Reported by the chain verification when the certificate doesn't match the server name
+const CERT_E_CN_NO_MATCH: i32 = 0x800B010Fu32 as i32;
+
 fn convert_protocols(protocols: &[::Protocol]) -> Vec<Protocol> {
     protocols
         .iter()
@@ -168,6 +171,16 @@ impl TlsConnectorBuilder {
         Ok(())
     }
 
+    pub fn sni_name(&mut self, name: &str) -> Result<(), Error> {
+        self.0.sni_name = Some(name.to_owned());
+        Ok(())
+    }
+
+    pub fn danger_accept_invalid_hostnames(&mut self, accept_invalid_hostnames: bool) -> Result<(), Error> {
+        self.0.accept_invalid_hostnames = accept_invalid_hostnames;
+        Ok(())
+    }
+
     pub fn build(self) -> Result<TlsConnector, Error> {
         Ok(self.0)
     }
@@ -178,6 +191,8 @@ pub struct TlsConnector {
     cert: Option<CertContext>,
     roots: CertStore,
     protocols: Vec<Protocol>,
+    sni_name: Option<String>,
+    accept_invalid_hostnames: bool,
     callback: Option<Arc<Fn(tls_stream::CertValidationResult) -> io::Result<()> + Sync + Send>>,
 }
 
@@ -187,6 +202,8 @@ impl TlsConnector {
             cert: None,
             roots: try!(Memory::new()).into_store(),
             protocols: vec![Protocol::Tls10, Protocol::Tls11, Protocol::Tls12],
+            sni_name: None,
+            accept_invalid_hostnames: false,
             callback: None,
         }))
     }
@@ -221,11 +238,26 @@ impl TlsConnector {
         let cred = try!(builder.acquire(Direction::Outbound));
         let mut builder = tls_stream::Builder::new();
         if let Some(domain) = domain {
-            builder.domain(domain);
+            builder.domain(self.sni_name.as_ref().map(|s| &s[..]).unwrap_or(domain));
         }
-        if let Some(ref callback) = self.callback {
-            let callback = callback.clone();
-            builder.verify_callback(move |r| callback(r));
+        let accept_invalid_hostnames = self.accept_invalid_hostnames;
+        if accept_invalid_hostnames || self.callback.is_some() {
+            let callback = self.callback.clone();
+            builder.verify_callback(move |r| {
+                if accept_invalid_hostnames {
+                    let name_mismatch = match r.result() {
+                        Err(ref e) => e.raw_os_error() == Some(CERT_E_CN_NO_MATCH),
+                        Ok(()) => false,
+                    };
+                    if name_mismatch && callback.is_none() {
+                        return Ok(());
+                    }
+                }
+                match callback {
+                    Some(ref callback) => callback(r),
+                    None => r.result(),
+                }
+            });
         }
         builder.cert_store(self.roots.clone());
         match builder.connect(cred, stream) {
@@ -291,6 +323,11 @@ impl<S: io::Read + io::Write> TlsStream<S> {
         Ok(self.0.get_buf().len())
     }
 
+    pub fn peer_certificate_chain(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let cert = try!(self.0.peer_certificate());
+        Ok(vec![cert.to_der().to_vec()])
+    }
+
     pub fn shutdown(&mut self) -> io::Result<()> {
         try!(self.0.shutdown());
         Ok(())
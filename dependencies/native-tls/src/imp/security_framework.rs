@@ -225,6 +225,16 @@ impl TlsConnectorBuilder {
         Ok(())
     }
 
+    pub fn sni_name(&mut self, name: &str) -> Result<(), Error> {
+        self.0.sni_name = Some(name.to_owned());
+        Ok(())
+    }
+
+    pub fn danger_accept_invalid_hostnames(&mut self, accept_invalid_hostnames: bool) -> Result<(), Error> {
+        self.0.accept_invalid_hostnames = accept_invalid_hostnames;
+        Ok(())
+    }
+
     pub fn build(self) -> Result<TlsConnector, Error> {
         Ok(self.0)
     }
@@ -235,6 +245,8 @@ pub struct TlsConnector {
     pkcs12: Option<Pkcs12>,
     protocols: Vec<Protocol>,
     roots: Vec<SecCertificate>,
+    sni_name: Option<String>,
+    accept_invalid_hostnames: bool,
 }
 
 impl TlsConnector {
@@ -243,6 +255,8 @@ impl TlsConnector {
             pkcs12: None,
             protocols: vec![Protocol::Tlsv10, Protocol::Tlsv11, Protocol::Tlsv12],
             roots: vec![],
+            sni_name: None,
+            accept_invalid_hostnames: false,
         }))
     }
 
@@ -250,6 +264,12 @@ impl TlsConnector {
     where
         S: io::Read + io::Write,
     {
+        // Secure Transport verifies whatever name it sends through SNI, so skipping the
+        // hostname check means not sending one at all
+        if self.accept_invalid_hostnames {
+            return self.connect_inner(None, stream);
+        }
+        let domain = self.sni_name.as_ref().map(|s| &s[..]).unwrap_or(domain);
         self.connect_inner(Some(domain), stream)
     }
 
@@ -359,6 +379,18 @@ impl<S: io::Read + io::Write> TlsStream<S> {
         Ok(try!(self.0.context().buffered_read_size()))
     }
 
+    pub fn peer_certificate_chain(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let trust = match try!(self.0.context().peer_trust()) {
+            Some(trust) => trust,
+            None => return Ok(vec![]),
+        };
+
+        Ok((0..trust.certificate_count())
+            .filter_map(|i| trust.certificate_at_index(i))
+            .map(|cert| cert.to_der())
+            .collect())
+    }
+
     pub fn shutdown(&mut self) -> io::Result<()> {
         try!(self.0.close());
         Ok(())
@@ -337,10 +337,42 @@ impl TlsConnectorBuilder {
     Ok(self)
   }
 
-  pub fn what_the_fuck(mut self, cert: Certificate) -> Result<TlsConnector> {
-    &self.0.add_root_certificate(cert.0)?;
-    let connector = self.0.build()?;
-    Ok(TlsConnector(connector))
+  /// Adds several certificates to the set of roots that the connector will trust.
+  pub fn add_root_certificates<I>(&mut self, certs: I) -> Result<&mut TlsConnectorBuilder>
+    where I: IntoIterator<Item = Certificate>
+  {
+    for cert in certs {
+      self.0.add_root_certificate(cert.0)?;
+    }
+    Ok(self)
+  }
+
+  /// Sets the name sent through SNI and checked against the server's certificate, instead of
+  /// the domain passed to `connect`.
+  ///
+  /// Useful for servers reached through an address their certificate doesn't cover, like a
+  /// local service listening on `127.0.0.1` with a certificate for a fixed host name.
+  pub fn sni_name(&mut self, name: &str) -> Result<&mut TlsConnectorBuilder> {
+    self.0.sni_name(name)?;
+    Ok(self)
+  }
+
+  /// Controls the use of hostname verification.
+  ///
+  /// Defaults to `false`. The certificate chain is still validated against the trusted roots.
+  /// The SNI name is still sent on all platforms but OSX, where Secure Transport can't send it
+  /// without also verifying it.
+  ///
+  /// # Warning
+  ///
+  /// You should think very carefully before you use this method. If hostname verification is not
+  /// used, *any* valid certificate for *any* site will be trusted for use from any other. This
+  /// introduces a significant vulnerability to man-in-the-middle attacks.
+  pub fn danger_accept_invalid_hostnames(&mut self,
+                                         accept_invalid_hostnames: bool)
+                                         -> Result<&mut TlsConnectorBuilder> {
+    self.0.danger_accept_invalid_hostnames(accept_invalid_hostnames)?;
+    Ok(self)
   }
 
   /// Consumes the builder, returning a `TlsConnector`.
@@ -526,6 +558,14 @@ impl<S: io::Read + io::Write> TlsStream<S> {
     Ok(self.0.buffered_read_size()?)
   }
 
+  /// Returns the DER encoded certificates the peer presented during the
+  /// handshake, its own certificate first.
+  ///
+  /// SChannel only exposes the peer's own certificate.
+  pub fn peer_certificate_chain(&self) -> Result<Vec<Vec<u8>>> {
+    Ok(self.0.peer_certificate_chain()?)
+  }
+
   /// Shuts down the TLS session.
   pub fn shutdown(&mut self) -> io::Result<()> {
     self.0.shutdown()?;
@@ -184,6 +184,192 @@ fn server_untrusted() {
     p!(j.join());
 }
 
+#[test]
+fn server_sni_name() {
+    let buf = include_bytes!("../test/identity.p12");
+    let pkcs12 = p!(Pkcs12::from_der(buf, "mypass"));
+    let builder = p!(TlsAcceptor::builder(pkcs12));
+    let builder = p!(builder.build());
+
+    let listener = p!(TcpListener::bind("0.0.0.0:0"));
+    let port = p!(listener.local_addr()).port();
+
+    let j = thread::spawn(move || {
+        let socket = p!(listener.accept()).0;
+        let mut socket = p!(builder.accept(socket));
+
+        let mut buf = [0; 5];
+        p!(socket.read_exact(&mut buf));
+        assert_eq!(&buf, b"hello");
+
+        p!(socket.write_all(b"world"));
+    });
+
+    let root_ca = include_bytes!("../test/root-ca.der");
+    let root_ca = Certificate::from_der(root_ca).unwrap();
+
+    let socket = p!(TcpStream::connect(("localhost", port)));
+    let mut builder = p!(TlsConnector::builder());
+    p!(builder.add_root_certificate(root_ca));
+    p!(builder.sni_name("foobar.com"));
+    let builder = p!(builder.build());
+    let mut socket = p!(builder.connect("localhost", socket));
+
+    p!(socket.write_all(b"hello"));
+    let mut buf = vec![];
+    p!(socket.read_to_end(&mut buf));
+    assert_eq!(buf, b"world");
+
+    p!(j.join());
+}
+
+#[test]
+fn server_peer_certificate_chain() {
+    let buf = include_bytes!("../test/identity.p12");
+    let pkcs12 = p!(Pkcs12::from_der(buf, "mypass"));
+    let builder = p!(TlsAcceptor::builder(pkcs12));
+    let builder = p!(builder.build());
+
+    let listener = p!(TcpListener::bind("0.0.0.0:0"));
+    let port = p!(listener.local_addr()).port();
+
+    let j = thread::spawn(move || {
+        let socket = p!(listener.accept()).0;
+        let mut socket = p!(builder.accept(socket));
+
+        p!(socket.write_all(b"world"));
+    });
+
+    let root_ca = include_bytes!("../test/root-ca.der");
+    let root_ca = Certificate::from_der(root_ca).unwrap();
+
+    let socket = p!(TcpStream::connect(("localhost", port)));
+    let mut builder = p!(TlsConnector::builder());
+    p!(builder.add_root_certificate(root_ca));
+    let builder = p!(builder.build());
+    let mut socket = p!(builder.connect("foobar.com", socket));
+
+    let chain = p!(socket.peer_certificate_chain());
+    assert!(!chain.is_empty());
+    p!(Certificate::from_der(&chain[0]));
+
+    let mut buf = vec![];
+    p!(socket.read_to_end(&mut buf));
+    assert_eq!(buf, b"world");
+
+    p!(j.join());
+}
+
+#[test]
+fn server_accept_invalid_hostnames() {
+    let buf = include_bytes!("../test/identity.p12");
+    let pkcs12 = p!(Pkcs12::from_der(buf, "mypass"));
+    let builder = p!(TlsAcceptor::builder(pkcs12));
+    let builder = p!(builder.build());
+
+    let listener = p!(TcpListener::bind("0.0.0.0:0"));
+    let port = p!(listener.local_addr()).port();
+
+    let j = thread::spawn(move || {
+        let socket = p!(listener.accept()).0;
+        let mut socket = p!(builder.accept(socket));
+
+        let mut buf = [0; 5];
+        p!(socket.read_exact(&mut buf));
+        assert_eq!(&buf, b"hello");
+
+        p!(socket.write_all(b"world"));
+    });
+
+    let root_ca = include_bytes!("../test/root-ca.der");
+    let root_ca = Certificate::from_der(root_ca).unwrap();
+
+    let socket = p!(TcpStream::connect(("localhost", port)));
+    let mut builder = p!(TlsConnector::builder());
+    p!(builder.add_root_certificate(root_ca));
+    p!(builder.danger_accept_invalid_hostnames(true));
+    let builder = p!(builder.build());
+    let mut socket = p!(builder.connect("rclient", socket));
+
+    p!(socket.write_all(b"hello"));
+    let mut buf = vec![];
+    p!(socket.read_to_end(&mut buf));
+    assert_eq!(buf, b"world");
+
+    p!(j.join());
+}
+
+#[test]
+fn server_wrong_hostname() {
+    let buf = include_bytes!("../test/identity.p12");
+    let pkcs12 = p!(Pkcs12::from_der(buf, "mypass"));
+    let builder = p!(TlsAcceptor::builder(pkcs12));
+    let builder = p!(builder.build());
+
+    let listener = p!(TcpListener::bind("0.0.0.0:0"));
+    let port = p!(listener.local_addr()).port();
+
+    let j = thread::spawn(move || {
+        let socket = p!(listener.accept()).0;
+        // FIXME should assert error
+        // https://github.com/steffengy/schannel-rs/issues/20
+        let _ = builder.accept(socket);
+    });
+
+    let root_ca = include_bytes!("../test/root-ca.der");
+    let root_ca = Certificate::from_der(root_ca).unwrap();
+
+    let socket = p!(TcpStream::connect(("localhost", port)));
+    let mut builder = p!(TlsConnector::builder());
+    p!(builder.add_root_certificate(root_ca));
+    let builder = p!(builder.build());
+    builder.connect("rclient", socket).unwrap_err();
+
+    p!(j.join());
+}
+
+#[test]
+fn server_multiple_roots() {
+    let buf = include_bytes!("../test/identity.p12");
+    let pkcs12 = p!(Pkcs12::from_der(buf, "mypass"));
+    let builder = p!(TlsAcceptor::builder(pkcs12));
+    let builder = p!(builder.build());
+
+    let listener = p!(TcpListener::bind("0.0.0.0:0"));
+    let port = p!(listener.local_addr()).port();
+
+    let j = thread::spawn(move || {
+        let socket = p!(listener.accept()).0;
+        let mut socket = p!(builder.accept(socket));
+
+        let mut buf = [0; 5];
+        p!(socket.read_exact(&mut buf));
+        assert_eq!(&buf, b"hello");
+
+        p!(socket.write_all(b"world"));
+    });
+
+    let other_ca = include_bytes!("../test/other-ca.der");
+    let root_ca = include_bytes!("../test/root-ca.der");
+    let roots = vec![
+        Certificate::from_der(other_ca).unwrap(),
+        Certificate::from_der(root_ca).unwrap(),
+    ];
+
+    let socket = p!(TcpStream::connect(("localhost", port)));
+    let mut builder = p!(TlsConnector::builder());
+    p!(builder.add_root_certificates(roots));
+    let builder = p!(builder.build());
+    let mut socket = p!(builder.connect("foobar.com", socket));
+
+    p!(socket.write_all(b"hello"));
+    let mut buf = vec![];
+    p!(socket.read_to_end(&mut buf));
+    assert_eq!(buf, b"world");
+
+    p!(j.join());
+}
+
 #[cfg(target_os = "windows")]
 #[test]
 fn schannel_verify_callback() {
@@ -0,0 +1,203 @@
+//! Starts the application when the user logs in
+//!
+//! Windows runs whatever the `Run` key of the current user lists, Linux desktops
+//! start the `.desktop` entries of the XDG autostart folder.
+
+use std::env;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::fs;
+#[cfg(any(windows, test))]
+use std::iter;
+use std::path::Path;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::path::PathBuf;
+#[cfg(windows)]
+use std::process::Command;
+
+use errors::*;
+
+#[cfg(windows)]
+static RUN_KEY: &'static str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+#[cfg(windows)]
+static VALUE_NAME: &'static str = "Dark Binding";
+
+/// Registers the running executable, launched with `args`, to start at login
+pub fn install(args: &[String]) -> Result<()> {
+  let exe = env::current_exe()?;
+
+  register(&exe, args)?;
+
+  info!("op=install_autostart exe={:?} args={:?} outcome=ok", exe, args);
+  println!("{} now starts when you log in", exe.display());
+
+  Ok(())
+}
+
+/// Stops starting at login
+pub fn uninstall() -> Result<()> {
+  unregister()?;
+
+  info!("op=uninstall_autostart outcome=ok");
+  println!("dark-binding no longer starts when you log in");
+
+  Ok(())
+}
+
+#[cfg(windows)]
+fn register(exe: &Path, args: &[String]) -> Result<()> {
+  reg(&["add", RUN_KEY, "/v", VALUE_NAME, "/t", "REG_SZ", "/d", &command_line(exe, args), "/f"])
+}
+
+/// Command line for the `Run` key, split again by the MSVCRT rules
+///
+/// The executable's path can't contain quotes and is taken as is between them.
+#[cfg(any(windows, test))]
+fn command_line(exe: &Path, args: &[String]) -> String {
+  let quoted: Vec<String> = args.iter().map(|arg| quote_arg(arg)).collect();
+
+  format!("\"{}\" {}", exe.display(), quoted.join(" ")).trim_right().to_owned()
+}
+
+/// Backslashes are only special before a quote, so those followed by a quote,
+/// or by the closing one, are doubled and the quote itself escaped
+#[cfg(any(windows, test))]
+fn quote_arg(arg: &str) -> String {
+  let mut quoted = String::from("\"");
+  let mut backslashes = 0;
+
+  for c in arg.chars() {
+    if c == '\\' {
+      backslashes += 1;
+      continue;
+    }
+
+    let count = if c == '"' { backslashes * 2 + 1 } else { backslashes };
+    quoted.extend(iter::repeat('\\').take(count));
+    quoted.push(c);
+    backslashes = 0;
+  }
+
+  quoted.extend(iter::repeat('\\').take(backslashes * 2));
+  quoted.push('"');
+
+  quoted
+}
+
+#[cfg(windows)]
+fn unregister() -> Result<()> {
+  reg(&["delete", RUN_KEY, "/v", VALUE_NAME, "/f"])
+}
+
+#[cfg(windows)]
+fn reg(args: &[&str]) -> Result<()> {
+  let output = Command::new("reg").args(args).output()?;
+
+  if !output.status.success() {
+    bail!("reg {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+  }
+
+  Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn register(exe: &Path, args: &[String]) -> Result<()> {
+  let path = desktop_file()?;
+
+  fs::create_dir_all(path.parent().unwrap())?;
+  fs::write(&path, desktop_entry(exe, args)).chain_err(|| ErrorKind::ConfigIo("write".to_owned(), path))?;
+
+  Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn unregister() -> Result<()> {
+  let path = desktop_file()?;
+
+  if path.exists() {
+    fs::remove_file(&path).chain_err(|| ErrorKind::ConfigIo("remove".to_owned(), path))?;
+  }
+
+  Ok(())
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+fn register(_exe: &Path, _args: &[String]) -> Result<()> {
+  bail!("starting at login is only supported on Windows and Linux")
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+fn unregister() -> Result<()> {
+  bail!("starting at login is only supported on Windows and Linux")
+}
+
+/// `$XDG_CONFIG_HOME/autostart/dark-binding.desktop`, under `~/.config` by default
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_file() -> Result<PathBuf> {
+  let config_home = match env::var_os("XDG_CONFIG_HOME") {
+    Some(dir) => PathBuf::from(dir),
+    None => PathBuf::from(env::var_os("HOME").ok_or("unable to find the home directory")?).join(".config")
+  };
+
+  Ok(config_home.join("autostart").join("dark-binding.desktop"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_entry(exe: &Path, args: &[String]) -> String {
+  // Quoted arguments escape ", `, $ and \ with a backslash, which the string
+  // value escapes once more
+  let quote = |arg: &str| {
+    let escaped: String = arg
+      .chars()
+      .flat_map(|c| match c {
+        '"' | '`' | '$' => vec!['\\', '\\', c],
+        '\\' => vec!['\\'; 4],
+        c => vec![c]
+      })
+      .collect();
+
+    format!("\"{}\"", escaped)
+  };
+
+  let exec: Vec<String> = Some(exe.display().to_string())
+    .iter()
+    .chain(args)
+    .map(|arg| quote(arg))
+    .collect();
+
+  format!(
+    "[Desktop Entry]\nType=Application\nName=Dark Binding\nComment=Champion specific League of Legends bindings\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+    exec.join(" ")
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  /// Arguments survive the MSVCRT splitting, a trailing backslash included
+  fn quotes_run_command_line() {
+    let command = command_line(
+      Path::new(r"C:\Program Files\dark-binding.exe"),
+      &["--config-dir".to_owned(), r"C:\Games\League\".to_owned(), r#"a \"b\" c"#.to_owned()]
+    );
+
+    assert_eq!(
+      command,
+      r#""C:\Program Files\dark-binding.exe" "--config-dir" "C:\Games\League\\" "a \\\"b\\\" c""#
+    );
+    assert_eq!(command_line(Path::new("dark-binding.exe"), &[]), "\"dark-binding.exe\"");
+  }
+
+  #[test]
+  #[cfg(all(unix, not(target_os = "macos")))]
+  /// The entry runs the executable with its arguments quoted
+  fn quotes_exec_line() {
+    let entry = desktop_entry(
+      Path::new("/opt/dark binding/dark-binding"),
+      &["--config-dir".to_owned(), "/home/me/$League".to_owned()]
+    );
+
+    assert!(entry.contains("Exec=\"/opt/dark binding/dark-binding\" \"--config-dir\" \"/home/me/\\\\$League\"\n"));
+  }
+}
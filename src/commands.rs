@@ -0,0 +1,171 @@
+//! Subcommands working on a config folder directly, without a running League client
+
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde_json;
+
+use errors::*;
+use league_client::{self, ConfigFolder, ExportedBindings};
+
+pub fn subcommands<'a, 'b>() -> Vec<App<'a, 'b>> {
+  vec![
+    SubCommand::with_name("groups")
+      .about("Inspect the champion groups")
+      .setting(AppSettings::SubcommandRequiredElseHelp)
+      .subcommand(SubCommand::with_name("list").about("List groups, their champions and configs"))
+      .subcommand(SubCommand::with_name("check").about("Validate groups.toml against the cached champion list")),
+    SubCommand::with_name("apply")
+      .about("Link a group's config as the current one")
+      .arg(Arg::with_name("group").required(true)),
+    SubCommand::with_name("restore").about("Put the base config back in place"),
+    SubCommand::with_name("backup").about("Back up the base config"),
+    SubCommand::with_name("status").about("Show the linked group and the state of a running instance"),
+    SubCommand::with_name("diff")
+      .about("Show how a group's config differs from the base config")
+      .arg(Arg::with_name("group").required(true)),
+    SubCommand::with_name("export")
+      .about("Write groups and group configs to a single JSON file")
+      .arg(Arg::with_name("file").help("Defaults to the standard output")),
+    SubCommand::with_name("import")
+      .about("Replace groups and group configs with an exported file")
+      .arg(Arg::with_name("file").required(true))
+  ]
+}
+
+/// Runs the subcommand `name` against the config folder at `config_dir`
+pub fn run(name: &str, matches: &ArgMatches, config_dir: PathBuf, dry_run: bool) -> Result<()> {
+  let mut config = ConfigFolder::new(config_dir);
+  config.set_dry_run(dry_run);
+  config.use_last_profile()?;
+
+  match name {
+    "groups" => match matches.subcommand() {
+      ("list", _) => list_groups(&config),
+      ("check", _) => check_groups(&config),
+      _ => unreachable!()
+    },
+    "apply" => {
+      let group = matches.value_of("group").unwrap();
+
+      if !config.groups()?.groups.contains_key(group) {
+        bail!("there is no group named {}", group);
+      }
+
+      config.link_group(group)
+    }
+    "restore" => config.restore(),
+    "backup" => config.backup(),
+    "status" => status(&config),
+    "diff" => diff(&config, matches.value_of("group").unwrap()),
+    "export" => {
+      let bindings = config.export()?;
+
+      match matches.value_of("file") {
+        Some(file) => serde_json::to_writer_pretty(File::create(file)?, &bindings)?,
+        None => serde_json::to_writer_pretty(io::stdout(), &bindings)?
+      };
+
+      Ok(())
+    }
+    "import" => {
+      let file = matches.value_of("file").unwrap();
+      let bindings: ExportedBindings = serde_json::from_reader(File::open(file)?)
+        .chain_err(|| format!("{} is not an exported bindings file", file))?;
+
+      config.import(&bindings)
+    }
+    _ => unreachable!()
+  }
+}
+
+fn list_groups(config: &ConfigFolder) -> Result<()> {
+  let linked = config.linked_group();
+
+  for (group_name, champions) in config.groups()?.groups {
+    let marker = if linked.as_ref() == Some(&group_name) { "*" } else { " " };
+    let has_config = config.group_config(&group_name).exists();
+
+    println!(
+      "{} {} ({}): {}",
+      marker,
+      group_name,
+      if has_config { "has config" } else { "no config yet" },
+      champions.join(", ")
+    );
+  }
+
+  Ok(())
+}
+
+fn check_groups(config: &ConfigFolder) -> Result<()> {
+  let catalog = config.champion_catalog()?;
+
+  if catalog.is_none() {
+    println!("no cached champion list yet, champion names are not checked");
+  }
+
+  let groups = config.valid_groups(catalog.as_ref())?;
+
+  println!("{} groups ok", groups.groups.len());
+
+  Ok(())
+}
+
+fn status(config: &ConfigFolder) -> Result<()> {
+  println!("config folder: {}", config.path().display());
+
+  match config.linked_group() {
+    Some(group) => println!("linked group: {}", group),
+    None => println!("linked group: none, the base config is in place")
+  }
+
+  match config.profile() {
+    Some(profile) => println!("profile: {}", profile),
+    None => println!("profile: shared")
+  }
+
+  println!("backup: {}", if config.persisted_settings_backup().exists() { "yes" } else { "no" });
+
+  match league_client::send_request(&config.data_dir(), league_client::Request::Status) {
+    Ok(response) => match response.status {
+      Some(status) => {
+        println!(
+          "running instance: {:?}, summoner {}, region {}",
+          status.connection,
+          status.summoner.unwrap_or_else(|| "unknown".to_owned()),
+          status.region.unwrap_or_else(|| "unknown".to_owned())
+        );
+
+        if let Some(e) = status.last_error {
+          println!("last error: {} while {} ({})", e.message, e.operation, e.category);
+        }
+      }
+      None => println!("running instance: {}", response.error.unwrap_or_default())
+    },
+    Err(_) => println!("running instance: none")
+  }
+
+  Ok(())
+}
+
+fn diff(config: &ConfigFolder, group: &str) -> Result<()> {
+  let group_config = config.group_config(group);
+
+  if !group_config.exists() {
+    bail!("group {} has no config yet, it's created the first time the group is applied", group);
+  }
+
+  let changes = league_client::diff_settings(&config.base_settings()?, &league_client::read_settings_json(&group_config)?);
+
+  if changes.is_empty() {
+    println!("{} is identical to the base config", group);
+  }
+
+  for change in changes {
+    println!("{}", change);
+  }
+
+  Ok(())
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+use std::path::PathBuf;
 use serde_json;
 use websocket;
 use reqwest;
@@ -15,9 +17,90 @@ error_chain! {
   }
 
   errors {
+    LcuApi(status: u16, code: String, message: String) {
+      display("LCU API error {} {}: {}", status, code, message)
+      description("LCU API error")
+    }
+
+    CertificateVerification(reason: String) {
+      display("unable to verify the LCU certificate ({}), if Riot rotated it point --lcu-root-cert to the new root", reason)
+      description("unable to verify the LCU certificate")
+    }
+
+    ConfigIo(action: String, path: PathBuf) {
+      display("unable to {} {:?}", action, path)
+      description("unable to access a config file")
+    }
+
+    Parse(what: String) {
+      display("unable to parse {}", what)
+      description("unable to parse a file")
+    }
+
+    LinkPermission(path: PathBuf) {
+      display("not allowed to link {:?}, on Windows enable Developer Mode or run as administrator", path)
+      description("not allowed to create a symbolic link")
+    }
+
+    UpdateVerification(reason: String) {
+      display("update rejected, {}", reason)
+      description("update rejected")
+    }
+
+    InvalidGroups(problems: Vec<String>) {
+      display("invalid champion groups: {}", problems.join("; "))
+      description("invalid champion groups")
+    }
+
+    TrayUnavailable(reason: String) {
+      display("system tray unavailable: {}", reason)
+      description("system tray unavailable")
+    }
+
     ForceShutdown {
       display("force shutdown")
       description("force shutdown")
     }
   }
 }
+
+/// What kind of failure an error is, as reported with the last error
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ErrorCategory {
+  ConfigIo,
+  LcuApi,
+  Parse,
+  LinkPermission,
+  Other
+}
+
+impl fmt::Display for ErrorCategory {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(match *self {
+      ErrorCategory::ConfigIo => "config_io",
+      ErrorCategory::LcuApi => "lcu_api",
+      ErrorCategory::Parse => "parse",
+      ErrorCategory::LinkPermission => "link_permission",
+      ErrorCategory::Other => "other"
+    })
+  }
+}
+
+impl Error {
+  /// Category of the outermost error, chained errors are described by it
+  pub fn category(&self) -> ErrorCategory {
+    match *self.kind() {
+      ErrorKind::ConfigIo(..) | ErrorKind::Io(_) => ErrorCategory::ConfigIo,
+      ErrorKind::LcuApi(..) | ErrorKind::Request(_) | ErrorKind::UrlParse(_) | ErrorKind::WebSocketError(_) => {
+        ErrorCategory::LcuApi
+      }
+      ErrorKind::Parse(_) | ErrorKind::JsonSerde(_) | ErrorKind::TomlDe(_) | ErrorKind::ParseInt(_) => {
+        ErrorCategory::Parse
+      }
+      ErrorKind::InvalidGroups(_) => ErrorCategory::Parse,
+      ErrorKind::LinkPermission(_) => ErrorCategory::LinkPermission,
+      _ => ErrorCategory::Other
+    }
+  }
+}
@@ -1,10 +1,6 @@
-use std::{fs, os};
-use std::io::Write;
-use std::borrow::Borrow;
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
-use reqwest::Response;
-use native_tls::{Certificate, TlsConnector};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 use websocket::{Message, OwnedMessage, WebSocketError};
 use websocket::ClientBuilder;
 use websocket::futures::{Future, Sink, Stream};
@@ -14,16 +10,35 @@ use tokio_core::reactor::Core;
 
 use CERTIFICATE;
 use errors::*;
+use trust::Trust;
+use updater::{self, Update};
 
-use league_client::DEFAULT_GROUPS_TOML;
 use league_client::structs::*;
+use league_client::config::{profile_name, ConfigFolder};
+use league_client::settings::Settings;
+use league_client::lcu::LcuClient;
+use league_client::retry::RetryPolicy;
 use league_client::util::*;
+use league_client::tracker::ChampionTracker;
+use league_client::recording::Recorder;
+use league_client::notify::Notification;
+use league_client::reporter::Reporter;
+use league_client::status::{ConnectionState, SharedStatus};
 use league_client::websocket::LeagueSocketHandler;
+use league_client::live_client;
 
 pub enum LeagueClientFn {
   BackupConfig,
   RestoreConfig,
   ReloadGroups,
+  ReloadSettings,
+  /// Links a group's config right away, until the next champion pick
+  ApplyGroup(String),
+  ResetGroup(String),
+  /// Installs a downloaded and verified release
+  InstallUpdate(String),
+  /// Another launch found this instance running, with its arguments
+  Launched(Vec<String>),
   Shutdown,
   Message(OwnedMessage)
 }
@@ -37,34 +52,111 @@ pub struct Credentials {
 
 pub struct LeagueClient {
   credentials: Credentials,
-  config_folder: PathBuf,
+  trust: Trust,
+  lcu: LcuClient,
+  startup_retry: RetryPolicy,
+  config: ConfigFolder,
   region: Option<String>,
   local_summoner: Option<LocalSummoner>,
   champion_names: HashMap<String, i32>,
-  champion_groups: HashMap<i32, String>
+  champion_groups: HashMap<i32, String>,
+  /// Every account uses the shared groups instead of its own profile
+  share_profiles: bool,
+  pub champion_tracker: ChampionTracker,
+  recorder: Option<Recorder>,
+  status: SharedStatus,
+  reporter: Reporter,
+  /// Asks the game's live client API for the champion, off while replaying
+  /// since whatever game runs now isn't the recorded one
+  live_client: bool,
+  /// The game has the linked config open, it mustn't be swapped under it
+  game_in_progress: bool,
+  shutdown_requested: bool
 }
 
 impl LeagueClient {
   pub fn new(credentials: Credentials, config_directory: PathBuf) -> LeagueClient {
+    let status = SharedStatus::new(config_directory.clone());
+
     LeagueClient {
+      trust: Trust::from_der(CERTIFICATE),
+      lcu: LcuClient::new(credentials.clone()),
+      startup_retry: RetryPolicy::startup(),
       credentials: credentials,
-      config_folder: config_directory,
+      config: ConfigFolder::new(config_directory),
       region: None,
       local_summoner: None,
       champion_names: HashMap::new(),
-      champion_groups: HashMap::new()
+      champion_groups: HashMap::new(),
+      share_profiles: false,
+      champion_tracker: ChampionTracker::new(Duration::from_millis(1500)),
+      recorder: None,
+      reporter: Reporter::new(status.clone()),
+      status: status,
+      live_client: true,
+      game_in_progress: false,
+      shutdown_requested: false
     }
   }
 
-  fn persisted_settings(&self) -> PathBuf {
-    self.config_folder.join("PersistedSettings.json")
+  /// Verifies the LCU certificate against `trust` for REST and websocket calls
+  pub fn with_trust(mut self, trust: &Trust) -> Result<LeagueClient> {
+    self.lcu = self.lcu.with_http_client(trust.http_client()?);
+    self.trust = trust.clone();
+
+    Ok(self)
+  }
+
+  /// Trusts the DER encoded `der` instead of the bundled LCU root certificate
+  pub fn with_root_certificate(self, der: &[u8]) -> Result<LeagueClient> {
+    self.with_trust(&Trust::from_der(der))
   }
 
-  fn persisted_settings_backup(&self) -> PathBuf {
-    let mut p = self.persisted_settings();
-    p.set_extension("bak");
+  /// Only prints the filesystem operations of config swaps when enabled
+  pub fn set_dry_run(&mut self, dry_run: bool) {
+    self.config.set_dry_run(dry_run);
+  }
 
-    p
+  /// Asks the game's live client API when the LCU doesn't know the champion
+  pub fn set_live_client(&mut self, enabled: bool) {
+    self.live_client = enabled;
+  }
+
+  pub fn uses_live_client(&self) -> bool {
+    self.live_client
+  }
+
+  /// Records every websocket frame received to `path`
+  pub fn record_to(&mut self, path: &Path) -> Result<()> {
+    self.recorder = Some(Recorder::create(path)?);
+
+    Ok(())
+  }
+
+  /// Connection state, summoner and linked group, shared with the control endpoint
+  pub fn status(&self) -> SharedStatus {
+    self.status.clone()
+  }
+
+  /// Sets the client state without asking the LCU, used to replay recordings
+  pub fn restore_session(&mut self, summoner: Option<LocalSummoner>, champions: HashMap<String, i32>) -> Result<()> {
+    self.status.set_summoner(summoner.as_ref().map(|s| s.display_name.clone()));
+    self.local_summoner = summoner;
+    self.champion_names = champions;
+
+    self.update_champion_groups()
+  }
+
+  /// Sets the retry policies used for regular calls and for the calls made
+  /// while the client is starting up
+  pub fn set_retry_policies(&mut self, regular: RetryPolicy, startup: RetryPolicy) {
+    self.lcu = self.lcu.clone().with_retry(regular);
+    self.startup_retry = startup;
+  }
+
+  /// LCU client with the startup retry policy
+  fn startup_lcu(&self) -> LcuClient {
+    self.lcu.clone().with_retry(self.startup_retry.clone())
   }
 
   pub fn local_summoner(&self) -> Option<LocalSummoner> {
@@ -74,15 +166,87 @@ impl LeagueClient {
     }
   }
 
-  /// Reload champion group associations from file
+  /// Looks up a champion ID by name, ignoring spaces, ticks and case
+  pub fn champion_id(&self, name: &str) -> Option<i32> {
+    self.champion_names.get(&normalize_champion_name(name)).cloned()
+  }
+
+  /// Applies `settings` to the swap logic
+  pub fn apply_settings(&mut self, settings: &Settings) {
+    ::logging::set_verbose(settings.logging.verbose);
+
+    self.champion_tracker.set_debounce(Duration::from_millis(settings.swap_debounce_ms));
+    self.config.set_shared_files(settings.shared_files.clone());
+    self.reporter.set_notifications(settings.notifications.clone());
+    self.share_profiles = settings.share_profiles;
+
+    self.set_retry_policies(
+      RetryPolicy {
+        attempts: settings.retry.attempts,
+        ..RetryPolicy::default()
+      },
+      RetryPolicy {
+        attempts: settings.retry.startup_attempts,
+        ..RetryPolicy::startup()
+      }
+    );
+  }
+
+  /// Reload settings.toml and apply it
+  pub fn reload_settings(&mut self) -> Result<()> {
+    let settings = self.config.settings()?;
+    self.apply_settings(&settings);
+
+    // Sharing profiles may have been turned on or off
+    if self.switch_profile()? {
+      self.update_champion_groups()?;
+    }
+
+    Ok(())
+  }
+
+  /// Uses the profile of the logged in account, or the shared groups, returns
+  /// whether it changed
+  ///
+  /// A group linked from the previous profile is unlinked first, its config
+  /// isn't the new account's.
+  fn switch_profile(&mut self) -> Result<bool> {
+    let profile = match (self.region.as_ref(), self.local_summoner.as_ref()) {
+      _ if self.share_profiles => None,
+      (Some(region), Some(summoner)) => Some(profile_name(region, summoner.account_key())),
+      _ => return Ok(false)
+    };
+
+    if profile == self.config.profile() {
+      return Ok(false);
+    }
+
+    if self.config.linked_group().is_some() {
+      self.config.restore()?;
+    }
+
+    self.config.use_profile(profile.as_ref().map(|p| &p[..]))?;
+    self.status.set_profile(profile.clone());
+
+    info!("op=switch_profile profile={:?} outcome=ok", profile);
+
+    Ok(true)
+  }
+
+  /// Reload champion group associations from file, keeping the current ones if
+  /// the file can't be read
+  ///
+  /// Unknown champions and unusable groups are left out with a warning, the
+  /// rest of the groups still apply.
   fn update_champion_groups(&mut self) -> Result<()> {
-    let groups_toml = self.config_folder.join(".dark-binding").join("groups.toml");
+    let catalog = if self.champion_names.is_empty() { None } else { Some(&self.champion_names) };
+    let (groups, problems) = self.config.usable_groups(catalog)?;
 
-    if !groups_toml.exists() {
-      create_default_groups_toml(groups_toml.parent().unwrap());
+    for problem in &problems {
+      warn!("op=load_groups outcome=skipped problem={:?}", problem);
     }
 
-    let groups = read_toml(&groups_toml)?.groups;
+    let groups = groups.groups;
 
     self.champion_groups.clear();
 
@@ -109,180 +273,130 @@ impl LeagueClient {
   /// Backs up the existing PersistedSettings.json if it is the default one
   /// ex: doesn't belong to any group
   pub fn backup_config(&self) -> Result<()> {
-    debug!("backing up config");
-    let persisted_settings = self.persisted_settings();
-    let backup = self.persisted_settings_backup();
-
-    if persisted_settings.exists() && !is_symlink(&persisted_settings)? {
-      fs::copy(persisted_settings, backup)?;
-    }
-
-    Ok(())
+    self.config.backup()
   }
 
   /// Restores the original PersistedSettings.json and sync any non-keybind
   /// settings changes
   pub fn restore_config(&self) -> Result<()> {
-    let current_settings_loc = self.persisted_settings();
-    let backup_loc = self.persisted_settings_backup();
-
-    if backup_loc.exists() && !current_settings_loc.exists() {
-      debug!("backup found but no current config");
+    self.config.restore()
+  }
 
-      fs::copy(&backup_loc, &current_settings_loc)?;
-      fs::remove_file(&backup_loc);
+  /// Load config for a champion if it belongs to a group
+  ///
+  /// Creates a new config file if it doesn't exist already, does nothing if
+  /// the champion's group is already the one linked. Returns what to notify,
+  /// errors are left to the caller to report
+  pub fn load_champion_config(&self, champion_id: i32) -> Result<Notification> {
+    match self.champion_groups.get(&champion_id) {
+      Some(group_name) => self
+        .config
+        .link_group(group_name)
+        .map(|_| Notification::GroupApplied(group_name.to_owned())),
+      _ => self
+        .restore_config()
+        .map(|_| Notification::NotInGroup(self.champion_name(champion_id)))
     }
+  }
 
-    let real_loc = match current_settings_loc.read_link() {
-      Ok(location) => location,
-      _ => {
-        debug!("not a symlink, aborting");
-        // it's not one of our links, nothing left to do
-        return Ok(());
-      }
-    };
-
-    debug!("restoring currently symlinked config {:?}", real_loc);
-
-    // Something went wrong and the backup got deleted, make whatever
-    // config is currently loaded the permanent one
-    if !backup_loc.exists() {
-      fs::remove_file(&current_settings_loc)?;
-      fs::copy(real_loc, current_settings_loc)?;
+  /// Champion name as shown by the client, falls back to its ID
+  fn champion_name(&self, champion_id: i32) -> String {
+    self
+      .config
+      .champion_aliases()
+      .ok()
+      .and_then(|aliases| aliases)
+      .and_then(|aliases| aliases.into_iter().find(|&(_, id)| id == champion_id))
+      .map(|(alias, _)| alias)
+      .unwrap_or_else(|| format!("champion {}", champion_id))
+  }
 
-      return Ok(());
-    }
+  fn update_local_structs(&mut self) -> Result<()> {
+    let lcu = self.startup_lcu();
 
-    let current_settings = read_settings_json(&real_loc)?;
-    let next_game_cfg = current_settings
-      .files
-      .into_iter()
-      .find(|f| f.name == "Game.cfg");
+    let local_summoner = lcu
+      .current_summoner()
+      .chain_err(|| "unable to get local summoner, check if you're logged in")?;
 
-    match next_game_cfg {
-      Some(cfg) => {
-        let mut final_settings = read_settings_json(&backup_loc)?;
+    let champions = lcu
+      .champions_minimal(&local_summoner.summoner_id)
+      .chain_err(|| "unable to get champion list from client")?;
 
-        for file in final_settings.files.iter_mut() {
-          if file.name == "Game.cfg" {
-            *file = cfg;
+    let aliases: BTreeMap<String, i32> = champions
+      .iter()
+      .filter(|e| e.id > 0)
+      .map(|e| (e.alias.clone(), e.id))
+      .collect();
 
-            break;
-          }
-        }
+    aliases.iter().for_each(|(alias, id)| {
+      self
+        .champion_names
+        .insert(normalize_champion_name(alias), *id);
+    });
 
-        write_settings_json(&backup_loc, &final_settings)?;
-      }
-      _ => {}
-    };
+    self.config.save_champion_catalog(&aliases)?;
 
-    fs::remove_file(&current_settings_loc);
-    fs::rename(backup_loc, current_settings_loc)?;
+    self.status.set_summoner(Some(local_summoner.display_name.clone()));
+    self.local_summoner = Some(local_summoner);
+    self.switch_profile()?;
+    self.update_champion_groups()?;
 
     Ok(())
   }
 
-  /// Load config for a champion if it belongs to a group
-  ///
-  /// Creates a new config file if it doesn't exist already
-  pub fn load_champion_config(&self, champion_id: i32) -> Result<()> {
-    self.restore_config()?;
-
-    let group_name = match self.champion_groups.get(&champion_id) {
-      Some(n) => n,
-      _ => return Ok(())
-    };
-
-    debug!("loading group name: {:?}", group_name);
-
-    let mut cfg_file_loc = self.config_folder.join(".dark-binding").join(group_name);
-    cfg_file_loc.set_extension("json");
-
-    let persisted_settings_loc = self.persisted_settings();
-
-    debug!("loading champion config {:?}", cfg_file_loc);
-
-    if !cfg_file_loc.exists() {
-      debug!("copying from {:?} to {:?}", persisted_settings_loc, cfg_file_loc);
-
-      ensure_dir(&cfg_file_loc.parent().unwrap())?;
-      fs::copy(&persisted_settings_loc, &cfg_file_loc)?;
-    }
-
-    self.backup_config()?;
+  /// Asks the client which region the account is logged in to
+  fn update_region(&mut self) -> Result<()> {
+    let rso_auth = self
+      .startup_lcu()
+      .rso_authorization()
+      .chain_err(|| "unable to get summoner region, check if you're logged in")?;
 
-    if persisted_settings_loc.exists() {
-      fs::remove_file(&persisted_settings_loc);
-    }
+    debug!("RSO auth successful: {:?}", rso_auth);
 
-    debug!("symlinking from {:?} to {:?}", cfg_file_loc, persisted_settings_loc);
-    // if cfg!(windows) {
-    os::windows::fs::symlink_file(cfg_file_loc, persisted_settings_loc).unwrap();
-    // } else {
-      // os::unix::fs::symlink(cfg_file_loc, persisted_settings_loc)?;
-    // }
+    self.region = Some(rso_auth.current_platform_id);
+    self.status.set_region(self.region.clone());
 
     Ok(())
   }
 
-  fn update_local_structs(&mut self) -> Result<()> {
-    let local_summoner: LocalSummoner = self
-      .get(
-        "/lol-summoner/v1/current-summoner",
-        None::<&[(String, String)]>
-      )?
-      .json()
-      .chain_err(|| "unable to get local summoner, check if you're logged in")?;
-
-
-    let champions: Vec<ChampionMinimal> = self
-      .get(
-        &format!(
-          "/lol-champions/v1/inventories/{}/champions-minimal",
-          &local_summoner.summoner_id
-        ),
-        None::<&[(String, String)]>
-      )?
-      .json()
-      .chain_err(|| "unable to get champion list from client")?;
-
-    champions.iter().filter(|e| e.id > 0).for_each(|e| {
-      self
-        .champion_names
-        .insert(normalize_champion_name(&e.alias), e.id);
-    });
+  /// Another account logged in, its region, champions and profile are loaded
+  pub fn account_changed(&mut self) -> Result<()> {
+    self.champion_names.clear();
+    self.update_region()?;
+    self.update_local_structs()
+  }
 
-    self.local_summoner = Some(local_summoner);
-    self.update_champion_groups()?;
+  pub fn set_game_in_progress(&mut self, game_in_progress: bool) {
+    self.game_in_progress = game_in_progress;
+  }
 
-    Ok(())
+  /// Logs, records and notifies failures
+  pub fn reporter(&self) -> &Reporter {
+    &self.reporter
   }
 
-  fn get<I, K, V>(&self, endpoint: &str, query: Option<I>) -> Result<Response>
-  where
-    I: IntoIterator,
-    I::Item: Borrow<(K, V)>,
-    K: AsRef<str>,
-    V: AsRef<str>
-  {
-    base_get(endpoint, &self.credentials, query)
-      .and_then(|res| res.error_for_status().map_err(|e| e.into()))
+  pub fn config(&self) -> &ConfigFolder {
+    &self.config
   }
 
-  // fn post<T: Serialize>(&self, endpoint: &str, json: &T) -> Result<Response> {
-  //   base_post(endpoint, &self.credentials, json)
-  //     .and_then(|res| res.error_for_status().map_err(|e| e.into()))
-  // }
+  pub fn lcu(&self) -> &LcuClient {
+    &self.lcu
+  }
 
   fn connect(&mut self, rx: UnboundedReceiver<LeagueClientFn>) -> Result<()> {
-    self.update_local_structs();
+    // Without summoner and champions there is nothing to swap, but the
+    // client is still watched so it can be controlled
+    let result = self.update_local_structs();
+    self.reporter.check("loading summoner and champions", result);
+
+    if let Some(ref mut recorder) = self.recorder {
+      let result = recorder.session(self.local_summoner.clone(), self.champion_names.clone());
+      self.reporter.check("recording", result);
+    }
 
     let mut core = Core::new().unwrap();
 
-    let tls_connector = TlsConnector::builder()
-      .unwrap()
-      .what_the_fuck(Certificate::from_der(CERTIFICATE).unwrap())
-      .ok();
+    let tls_connector = Some(self.trust.tls_connector()?);
 
     let (url, headers) = {
       let c = &self.credentials;
@@ -300,14 +414,21 @@ impl LeagueClient {
 
     debug!("Connecting to {}", url);
 
+    let status = self.status.clone();
+    status.set_connection(ConnectionState::Connecting);
+
     let f = ClientBuilder::new(&url)
       .unwrap()
       .add_protocol("wamp")
       .custom_headers(&headers)
       .async_connect_secure(tls_connector, &core.handle())
       .and_then(|(duplex, _)| duplex.send(Message::text("[5,\"OnJsonApiEvent\"]").into()))
+      .map(|duplex| {
+        status.set_connection(ConnectionState::Connected);
+        duplex
+      })
       .and_then(|duplex| {
-        let (_s, stream) = duplex.split();
+        let (sink, stream) = duplex.split();
 
         stream
           .map(|m| LeagueClientFn::Message(m))
@@ -315,55 +436,242 @@ impl LeagueClient {
           .for_each(|message| {
             match message {
               LeagueClientFn::BackupConfig => {
-                self.backup_config();
+                let result = self.backup_config();
+                self.reporter.check("backing up bindings", result);
               }
               LeagueClientFn::RestoreConfig => {
-                self.restore_config();
+                let result = self.restore_config().map(|_| Notification::Restored);
+                self.reporter.outcome("restoring bindings", result).ok();
               }
               LeagueClientFn::ReloadGroups => {
-                self.update_champion_groups();
+                let result = self.update_champion_groups();
+                self.reporter.check("reloading groups", result);
+              }
+              LeagueClientFn::ReloadSettings => {
+                let result = self.reload_settings();
+                self.reporter.check("reloading settings", result);
+              }
+              LeagueClientFn::ApplyGroup(group_name) => {
+                let result = self
+                  .config
+                  .link_group(&group_name)
+                  .map(|_| Notification::GroupApplied(group_name));
+                self.reporter.outcome("applying a group", result).ok();
+              }
+              LeagueClientFn::ResetGroup(group_name) => {
+                let result = self.config.reset_group(&group_name);
+                self.reporter.check("resetting a group", result);
+              }
+              LeagueClientFn::InstallUpdate(version) => {
+                let result = self.install_update(&version).map(|_| Notification::UpdateInstalled(version));
+                self.reporter.outcome("installing the update", result).ok();
+              }
+              LeagueClientFn::Launched(args) => {
+                // This instance keeps the options it started with, the new ones are only logged
+                info!("op=launch args={:?} outcome=handed_off", args);
+                self.reporter.notify(Notification::AlreadyRunning);
               }
               LeagueClientFn::Shutdown => {
-                self.shutdown();
+                self.shutdown_requested = true;
+
                 return Err(WebSocketError::NoDataAvailable);
               }
               LeagueClientFn::Message(m) => {
+                if let Some(ref mut recorder) = self.recorder {
+                  let result = recorder.frame(&m);
+                  self.reporter.check("recording", result);
+                }
+
                 self.on_message(m);
               }
             };
 
             Ok(())
           })
+          // Tells the client we're going away, whether asked to or not
+          .then(move |result| sink.send(OwnedMessage::Close(None)).then(move |_| result))
       });
 
-    core.run(f)?;
+    let result = core.run(f);
+
+    self.status.set_connection(ConnectionState::Disconnected);
+
+    if self.shutdown_requested {
+      self.shutdown()?;
+
+      bail!(ErrorKind::ForceShutdown);
+    }
+
+    result?;
 
     Ok(())
   }
 
   pub fn init(&mut self, rx: UnboundedReceiver<LeagueClientFn>) -> Result<()> {
-    let rso_auth: RSO = self
-      .get("/rso-auth/v1/authorization", None::<&[(String, String)]>)?
-      .json()
-      .chain_err(|| "unable to get summoner region, check if you're logged in")?;
-
-    debug!("RSO auth successful: {:?}", rso_auth);
+    self.reload_settings()?;
+    self.update_region()?;
 
-    self.region = Some(rso_auth.current_platform_id);
+    // The last run quit during a game, which is over unless it still serves its API
+    if !self.live_client || !live_client::game_running() {
+      let result = self.config.restore_pending().map(|_| ());
+      self.reporter.check("restoring bindings left linked by the last run", result);
+    }
 
     self.connect(rx)
   }
 
-  pub fn shutdown(&self) -> Result<()> {
-    debug!("Shutting down client forcefully");
+  /// Replaces the executable with the downloaded `version`
+  fn install_update(&self, version: &str) -> Result<()> {
+    updater::install(&Update {
+      version: version.to_owned(),
+      path: updater::download_path(&self.config.updates_dir(), version)?
+    })?;
+
+    self.status.set_update_available(None);
 
     Ok(())
   }
+
+  /// Puts the base config back before quitting
+  ///
+  /// A running game still has the linked config open and writes to it when it
+  /// ends, so the restore is left for the next launch instead.
+  pub fn shutdown(&self) -> Result<()> {
+    debug!("shutting down, game in progress: {}", self.game_in_progress);
+
+    if self.config.linked_group().is_none() {
+      return Ok(());
+    }
+
+    if self.game_in_progress {
+      return self.config.schedule_restore().map_err(|e| {
+        self.reporter.error("scheduling the restore of bindings", &e);
+        e
+      });
+    }
+
+    let result = self.restore_config().map(|_| Notification::Restored);
+
+    self.reporter.outcome("restoring bindings before quitting", result)
+  }
 }
 
-/// Copies the included default groups.toml to the .dark-binding config directory
-fn create_default_groups_toml(path: &Path) -> Result<()> {
-  ensure_dir(path)?;
+#[cfg(test)]
+mod tests {
+  use std::fs::{self, File};
+  use std::io::Write;
+  use std::path::Path;
+  use serde_json::Value;
+  use tempdir::TempDir;
+  use websocket::futures::sync::mpsc::unbounded;
+
+  use league_client::mock::{MockLcu, ROOT_CERTIFICATE};
+  use super::*;
+
+  static CHAMP_SELECT: &'static str = "/lol-champ-select/v1/session";
+
+  fn write_file(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    File::create(path).unwrap().write_all(contents.as_bytes()).unwrap();
+  }
+
+  /// Config folder with a base PersistedSettings.json and an `adc` group
+  fn config_folder() -> TempDir {
+    let dir = TempDir::new("dark-binding").unwrap();
+
+    write_file(
+      &dir.path().join("PersistedSettings.json"),
+      r#"{ "description": "base", "files": [{ "name": "Game.cfg", "sections": [] }] }"#
+    );
+    write_file(
+      &dir.path().join(".dark-binding").join("groups.toml"),
+      "[groups]\nadc = [\"Jinx\", \"Kog'Maw\"]\n"
+    );
 
-  Ok(fs::OpenOptions::new().write(true).create(true).open(path.join("groups.toml"))?.write_all(DEFAULT_GROUPS_TOML)?)
+    dir
+  }
+
+  fn locked_in(champion_id: i32) -> Value {
+    json!({
+      "actions": [[{ "actorCellId": 0, "championId": champion_id, "completed": true, "type": "pick" }]],
+      "localPlayerCellId": 0,
+      "myTeam": [{ "cellId": 0, "championId": champion_id, "championPickIntent": 0, "summonerId": 1 }],
+      "timer": { "phase": "BAN_PICK" }
+    })
+  }
+
+  fn run_session(mock: MockLcu, config: &Path) {
+    let lcu = mock.start();
+    let mut client = LeagueClient::new(lcu.credentials(), config.to_owned())
+      .with_root_certificate(ROOT_CERTIFICATE)
+      .unwrap();
+
+    let (_sender, rx) = unbounded();
+
+    client.init(rx).ok();
+
+    assert!(lcu.requests().contains(&"WEBSOCKET".to_owned()));
+  }
+
+  #[test]
+  /// Locking in a grouped champion links the group's config
+  fn links_group_config_on_lock_in() {
+    let config = config_folder();
+    let mock = MockLcu::new()
+      .champions(&[(222, "Jinx"), (96, "KogMaw")])
+      .event("Create", CHAMP_SELECT, json!({}))
+      .event("Update", CHAMP_SELECT, locked_in(96))
+      .event("Delete", CHAMP_SELECT, Value::Null);
+
+    run_session(mock, config.path());
+
+    let persisted_settings = config.path().join("PersistedSettings.json");
+    let group_config = config.path().join(".dark-binding/profiles/euw1-1/adc.json");
+
+    assert_eq!(persisted_settings.read_link().unwrap(), group_config);
+    assert!(config.path().join("PersistedSettings.bak").exists());
+  }
+
+  #[test]
+  /// Champions outside of any group leave the base config alone
+  fn ignores_ungrouped_champions() {
+    let config = config_folder();
+    let mock = MockLcu::new()
+      .champions(&[(222, "Jinx"), (1, "Annie")])
+      .event("Update", CHAMP_SELECT, locked_in(1))
+      .event("Delete", CHAMP_SELECT, Value::Null);
+
+    run_session(mock, config.path());
+
+    let persisted_settings = config.path().join("PersistedSettings.json");
+
+    assert!(!is_symlink(&persisted_settings).unwrap());
+    assert!(!config.path().join(".dark-binding/profiles/euw1-1/adc.json").exists());
+  }
+
+  #[test]
+  /// Restoring after a game puts the base config back in place
+  fn restores_base_config() {
+    let config = config_folder();
+    let mock = MockLcu::new()
+      .champions(&[(222, "Jinx")])
+      .event("Update", CHAMP_SELECT, locked_in(222))
+      .event("Delete", CHAMP_SELECT, Value::Null);
+
+    let lcu = mock.start();
+    let mut client = LeagueClient::new(lcu.credentials(), config.path().to_owned())
+      .with_root_certificate(ROOT_CERTIFICATE)
+      .unwrap();
+
+    let (_sender, rx) = unbounded();
+    client.init(rx).ok();
+    client.restore_config().unwrap();
+
+    let persisted_settings = config.path().join("PersistedSettings.json");
+    let settings = read_settings_json(&persisted_settings).unwrap();
+
+    assert!(!is_symlink(&persisted_settings).unwrap());
+    assert_eq!(settings.description, "base");
+    assert!(!config.path().join("PersistedSettings.bak").exists());
+  }
 }
@@ -0,0 +1,672 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use serde_json;
+use toml;
+
+use errors::*;
+
+use league_client::DEFAULT_GROUPS_TOML;
+use league_client::structs::{GroupsToml, PersistedSettings};
+use league_client::file_ops::FileOps;
+use league_client::settings::Settings;
+use league_client::util::*;
+
+/// Group configs and the groups file, everything needed to move bindings between installs
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportedBindings {
+  pub groups: GroupsToml,
+  pub configs: BTreeMap<String, PersistedSettings>
+}
+
+/// A League `Config` folder and the `.dark-binding` data kept inside it
+///
+/// Holds the config swap itself, so it works the same with or without a
+/// running League client. The profile in use is shared between clones, so the
+/// tray, editor and watcher follow account switches.
+#[derive(Debug, Clone)]
+pub struct ConfigFolder {
+  path: PathBuf,
+  ops: FileOps,
+  shared_files: Vec<String>,
+  profile: Arc<Mutex<Option<String>>>
+}
+
+impl ConfigFolder {
+  pub fn new(path: PathBuf) -> ConfigFolder {
+    ConfigFolder {
+      path: path,
+      ops: FileOps::default(),
+      shared_files: Settings::default().shared_files,
+      profile: Arc::new(Mutex::new(None))
+    }
+  }
+
+  /// Settings files synced back into the base config when a group is unlinked
+  pub fn set_shared_files(&mut self, shared_files: Vec<String>) {
+    self.shared_files = shared_files;
+  }
+
+  /// Only prints the filesystem operations of config swaps when enabled
+  pub fn set_dry_run(&mut self, dry_run: bool) {
+    self.ops = FileOps::new(dry_run);
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn data_dir(&self) -> PathBuf {
+    self.path.join(".dark-binding")
+  }
+
+  /// Verified downloads of newer releases
+  pub fn updates_dir(&self) -> PathBuf {
+    self.data_dir().join("updates")
+  }
+
+  /// Rotating log files of the application
+  pub fn logs_dir(&self) -> PathBuf {
+    self.data_dir().join("logs")
+  }
+
+  /// Profile whose groups and group configs are in use, `None` when shared
+  pub fn profile(&self) -> Option<String> {
+    self.profile.lock().unwrap().clone()
+  }
+
+  /// Where the groups and group configs in use are, the profile's folder or
+  /// the `.dark-binding` folder itself when shared
+  pub fn profile_dir(&self) -> PathBuf {
+    match self.profile() {
+      Some(profile) => self.profiles_dir().join(profile),
+      None => self.data_dir()
+    }
+  }
+
+  fn profiles_dir(&self) -> PathBuf {
+    self.data_dir().join("profiles")
+  }
+
+  /// Remembers the profile of the last account, for commands run without a client
+  fn last_profile_path(&self) -> PathBuf {
+    self.data_dir().join("profile")
+  }
+
+  pub fn groups_toml(&self) -> PathBuf {
+    self.profile_dir().join("groups.toml")
+  }
+
+  pub fn group_config(&self, group_name: &str) -> PathBuf {
+    let mut path = self.profile_dir().join(group_name);
+    path.set_extension("json");
+
+    path
+  }
+
+  pub fn persisted_settings(&self) -> PathBuf {
+    self.path.join("PersistedSettings.json")
+  }
+
+  pub fn persisted_settings_backup(&self) -> PathBuf {
+    let mut p = self.persisted_settings();
+    p.set_extension("bak");
+
+    p
+  }
+
+  /// Left when quitting during a game, the base config is restored on the next launch
+  fn pending_restore_marker(&self) -> PathBuf {
+    self.data_dir().join("restore-pending")
+  }
+
+  fn champion_catalog_path(&self) -> PathBuf {
+    self.data_dir().join("champions.json")
+  }
+
+  /// Group whose config is currently linked as PersistedSettings.json
+  pub fn linked_group(&self) -> Option<String> {
+    self
+      .persisted_settings()
+      .read_link()
+      .ok()
+      .and_then(|l| l.file_stem().map(|s| s.to_string_lossy().into_owned()))
+  }
+
+  /// Reads groups.toml, creating the default one first if there is none
+  pub fn groups(&self) -> Result<GroupsToml> {
+    let groups_toml = self.groups_toml();
+
+    if !groups_toml.exists() {
+      create_default_groups_toml(&self.profile_dir())?;
+    }
+
+    read_toml(&groups_toml)
+  }
+
+  /// Reads groups.toml and fails with every problem `validate_groups` finds
+  pub fn valid_groups(&self, catalog: Option<&HashMap<String, i32>>) -> Result<GroupsToml> {
+    let groups = self.groups()?;
+    let problems = validate_groups(&groups, catalog);
+
+    if !problems.is_empty() {
+      bail!(ErrorKind::InvalidGroups(problems));
+    }
+
+    Ok(groups)
+  }
+
+  /// Reads groups.toml leaving out what `validate_groups` would reject, with
+  /// the problems found
+  ///
+  /// Groups that can't name a file are left out, unknown champions are left
+  /// out of their group, so one typo doesn't unlink every other champion.
+  pub fn usable_groups(&self, catalog: Option<&HashMap<String, i32>>) -> Result<(GroupsToml, Vec<String>)> {
+    let groups = self.groups()?;
+    let problems = validate_groups(&groups, catalog);
+
+    let usable = groups
+      .groups
+      .into_iter()
+      .filter(|&(ref group_name, _)| is_usable_group_name(group_name))
+      .map(|(group_name, champions)| {
+        let champions = champions
+          .into_iter()
+          .filter(|c| catalog.map_or(true, |catalog| catalog.contains_key(&normalize_champion_name(c))))
+          .collect();
+
+        (group_name, champions)
+      })
+      .collect();
+
+    Ok((GroupsToml { groups: usable }, problems))
+  }
+
+  /// Validates `groups` like `valid_groups` and writes them to groups.toml
+  pub fn save_groups(&self, groups: &GroupsToml, catalog: Option<&HashMap<String, i32>>) -> Result<()> {
+    let problems = validate_groups(groups, catalog);
+
+    if !problems.is_empty() {
+      bail!(ErrorKind::InvalidGroups(problems));
+    }
+
+    self.ops.ensure_dir(&self.profile_dir())?;
+    self.ops.write_file(&self.groups_toml(), toml::to_string(groups)?.as_bytes())
+  }
+
+  /// Switches to the groups and group configs of `profile`, or to the shared ones
+  ///
+  /// A new profile starts as a copy of the shared groups and group configs.
+  pub fn use_profile(&self, profile: Option<&str>) -> Result<()> {
+    if let Some(profile) = profile {
+      let dir = self.profiles_dir().join(profile);
+
+      if !dir.exists() {
+        self.seed_profile(&dir)?;
+      }
+    }
+
+    *self.profile.lock().unwrap() = profile.map(str::to_owned);
+
+    self.ops.ensure_dir(&self.data_dir())?;
+    self.ops.write_file(&self.last_profile_path(), profile.unwrap_or("").as_bytes())
+  }
+
+  /// Uses the profile of the account last seen by a running instance, unless
+  /// profiles are shared
+  pub fn use_last_profile(&self) -> Result<()> {
+    if Settings::read(&self.data_dir()).unwrap_or_default().share_profiles {
+      return Ok(());
+    }
+
+    let profile = fs::read_to_string(self.last_profile_path())
+      .ok()
+      .map(|p| p.trim().to_owned())
+      .filter(|p| !p.is_empty());
+
+    *self.profile.lock().unwrap() = profile;
+
+    Ok(())
+  }
+
+  /// Copies the shared groups.toml and group configs into the profile folder `dir`
+  fn seed_profile(&self, dir: &Path) -> Result<()> {
+    let shared_groups = self.data_dir().join("groups.toml");
+
+    self.ops.ensure_dir(dir)?;
+
+    if !shared_groups.exists() {
+      return Ok(());
+    }
+
+    self.ops.copy(&shared_groups, &dir.join("groups.toml"))?;
+
+    for group_name in read_toml(&shared_groups)?.groups.keys() {
+      let file_name = format!("{}.json", group_name);
+      let shared_config = self.data_dir().join(&file_name);
+
+      if shared_config.exists() {
+        self.ops.copy(&shared_config, &dir.join(&file_name))?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Reads settings.toml, creating the default one first if there is none
+  pub fn settings(&self) -> Result<Settings> {
+    Settings::load(&self.data_dir())
+  }
+
+  /// Champion IDs by name, as last seen from the League client
+  pub fn champion_aliases(&self) -> Result<Option<BTreeMap<String, i32>>> {
+    let path = self.champion_catalog_path();
+
+    if !path.exists() {
+      return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_reader(File::open(path)?)?))
+  }
+
+  /// Champion IDs by normalized name, as last seen from the League client
+  pub fn champion_catalog(&self) -> Result<Option<HashMap<String, i32>>> {
+    Ok(self.champion_aliases()?.map(|aliases| {
+      aliases
+        .into_iter()
+        .map(|(alias, id)| (normalize_champion_name(&alias), id))
+        .collect()
+    }))
+  }
+
+  /// Caches the champion catalog, by alias, so groups can be checked and edited
+  /// without a running client
+  pub fn save_champion_catalog(&self, champions: &BTreeMap<String, i32>) -> Result<()> {
+    if self.ops.dry_run {
+      return Ok(());
+    }
+
+    ensure_dir(&self.data_dir())?;
+
+    Ok(serde_json::to_writer(File::create(self.champion_catalog_path())?, champions)?)
+  }
+
+  /// Settings the game uses when no group is linked
+  pub fn base_settings(&self) -> Result<PersistedSettings> {
+    let persisted_settings = self.persisted_settings();
+    let backup = self.persisted_settings_backup();
+
+    match persisted_settings.read_link() {
+      Ok(_) if backup.exists() => read_settings_json(&backup),
+      Ok(target) => read_settings_json(&target),
+      _ => read_settings_json(&persisted_settings)
+    }
+  }
+
+  /// Backs up the existing PersistedSettings.json if it is the default one
+  /// ex: doesn't belong to any group
+  pub fn backup(&self) -> Result<()> {
+    log_swap("backup", format!("config={:?}", self.path), || {
+      debug!("backing up config");
+      let persisted_settings = self.persisted_settings();
+      let backup = self.persisted_settings_backup();
+
+      if persisted_settings.exists() && !is_symlink(&persisted_settings)? {
+        self.ops.copy(&persisted_settings, &backup)?;
+      }
+
+      Ok(())
+    })
+  }
+
+  /// Restores the original PersistedSettings.json and sync any non-keybind
+  /// settings changes
+  pub fn restore(&self) -> Result<()> {
+    log_swap("restore", format!("config={:?} linked={:?}", self.path, self.linked_group()), || {
+      let current_settings_loc = self.persisted_settings();
+      let backup_loc = self.persisted_settings_backup();
+
+      if backup_loc.exists() && !current_settings_loc.exists() {
+        debug!("backup found but no current config");
+
+        self.ops.copy(&backup_loc, &current_settings_loc)?;
+        self.ops.remove(&backup_loc)?;
+      }
+
+      let real_loc = match current_settings_loc.read_link() {
+        Ok(location) => location,
+        _ => {
+          debug!("not a symlink, aborting");
+          // it's not one of our links, nothing left to do
+          return Ok(());
+        }
+      };
+
+      debug!("restoring currently symlinked config {:?}", real_loc);
+
+      // Something went wrong and the backup got deleted, make whatever
+      // config is currently loaded the permanent one
+      if !backup_loc.exists() {
+        self.ops.remove(&current_settings_loc)?;
+        self.ops.copy(&real_loc, &current_settings_loc)?;
+
+        return Ok(());
+      }
+
+      let current_settings = read_settings_json(&real_loc)?;
+      let shared_files: Vec<_> = current_settings
+        .files
+        .into_iter()
+        .filter(|f| self.shared_files.contains(&f.name))
+        .collect();
+
+      if !shared_files.is_empty() {
+        let base_settings = read_settings_json(&backup_loc)?;
+        let mut final_settings = read_settings_json(&backup_loc)?;
+
+        for shared_file in shared_files {
+          if let Some(file) = final_settings.files.iter_mut().find(|f| f.name == shared_file.name) {
+            *file = shared_file;
+          }
+        }
+
+        self.ops.write_settings(&backup_loc, &base_settings, &final_settings)?;
+      }
+
+      self.ops.remove(&current_settings_loc)?;
+      self.ops.rename(&backup_loc, &current_settings_loc)?;
+
+      Ok(())
+    })
+  }
+
+  /// Restores the base config on the next launch instead of now, for when the
+  /// game still has the linked group's config open
+  pub fn schedule_restore(&self) -> Result<()> {
+    info!("op=schedule_restore config={:?} linked={:?}", self.path, self.linked_group());
+
+    self.ops.ensure_dir(&self.data_dir())?;
+    self.ops.write_file(&self.pending_restore_marker(), b"")
+  }
+
+  /// Restores the base config if a restore was scheduled, returns whether it was
+  pub fn restore_pending(&self) -> Result<bool> {
+    let marker = self.pending_restore_marker();
+
+    if !marker.exists() {
+      return Ok(false);
+    }
+
+    self.restore()?;
+    self.ops.remove(&marker)?;
+
+    Ok(true)
+  }
+
+  /// Links a group's config as PersistedSettings.json
+  ///
+  /// Creates the group config from the current one if it doesn't exist already,
+  /// does nothing if the group is already the one linked
+  pub fn link_group(&self, group_name: &str) -> Result<()> {
+    let inputs = format!("group={:?} config={:?} linked={:?}", group_name, self.path, self.linked_group());
+
+    log_swap("link_group", inputs, || {
+      debug!("loading group name: {:?}", group_name);
+
+      let cfg_file_loc = self.group_config(group_name);
+
+      if self.persisted_settings().read_link().ok().map_or(false, |l| l == cfg_file_loc) {
+        debug!("group {} is already linked", group_name);
+
+        return Ok(());
+      }
+
+      self.restore()?;
+
+      let persisted_settings_loc = self.persisted_settings();
+
+      debug!("loading champion config {:?}", cfg_file_loc);
+
+      if !cfg_file_loc.exists() {
+        debug!("copying from {:?} to {:?}", persisted_settings_loc, cfg_file_loc);
+
+        self.ops.ensure_dir(&cfg_file_loc.parent().unwrap())?;
+        self.ops.copy(&persisted_settings_loc, &cfg_file_loc)?;
+      }
+
+      self.backup()?;
+
+      if persisted_settings_loc.exists() {
+        self.ops.remove(&persisted_settings_loc)?;
+      }
+
+      debug!("symlinking from {:?} to {:?}", cfg_file_loc, persisted_settings_loc);
+      self.ops.link(&cfg_file_loc, &persisted_settings_loc)?;
+
+      Ok(())
+    })
+  }
+
+  /// Overwrites a group's config with the base one, a linked group keeps its link
+  pub fn reset_group(&self, group_name: &str) -> Result<()> {
+    log_swap("reset_group", format!("group={:?} config={:?}", group_name, self.path), || {
+      debug!("resetting group {} to the base config", group_name);
+
+      let cfg_file_loc = self.group_config(group_name);
+
+      if !cfg_file_loc.exists() {
+        return Ok(());
+      }
+
+      let base_settings = self.base_settings()?;
+      let previous = read_settings_json(&cfg_file_loc)?;
+
+      self.ops.write_settings(&cfg_file_loc, &previous, &base_settings)
+    })
+  }
+
+  /// Collects groups.toml and every existing group config
+  pub fn export(&self) -> Result<ExportedBindings> {
+    let groups = self.groups()?;
+    let mut configs = BTreeMap::new();
+
+    for group_name in groups.groups.keys() {
+      let path = self.group_config(group_name);
+
+      if path.exists() {
+        configs.insert(group_name.to_owned(), read_settings_json(&path)?);
+      }
+    }
+
+    Ok(ExportedBindings {
+      groups: groups,
+      configs: configs
+    })
+  }
+
+  /// Writes exported groups and group configs, replacing the current ones
+  pub fn import(&self, bindings: &ExportedBindings) -> Result<()> {
+    let orphans: Vec<_> = bindings
+      .configs
+      .keys()
+      .filter(|group_name| !bindings.groups.groups.contains_key(*group_name))
+      .map(|group_name| format!("config {:?} doesn't belong to any group", group_name))
+      .collect();
+
+    if !orphans.is_empty() {
+      bail!(ErrorKind::InvalidGroups(orphans));
+    }
+
+    self.save_groups(&bindings.groups, None)?;
+
+    for (group_name, settings) in &bindings.configs {
+      self.ops.write_file(&self.group_config(group_name), serde_json::to_string_pretty(settings)?.as_bytes())?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Profile folder name of an account, from its region and `LocalSummoner::account_key`
+pub fn profile_name(region: &str, account_key: &str) -> String {
+  format!("{}-{}", region, account_key)
+    .to_lowercase()
+    .chars()
+    .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+    .collect()
+}
+
+/// Runs a swap operation, logging its inputs and outcome
+fn log_swap<F: FnOnce() -> Result<()>>(op: &str, inputs: String, f: F) -> Result<()> {
+  debug!("op={} {} outcome=started", op, inputs);
+
+  let result = f();
+
+  match result {
+    Ok(_) => info!("op={} {} outcome=ok", op, inputs),
+    Err(ref e) => error!("op={} {} outcome=error error={:?}", op, inputs, e.to_string())
+  }
+
+  result
+}
+
+/// Problems with a groups file, with `catalog` unknown champion names are reported too
+///
+/// A champion in several groups isn't a problem, the last group wins.
+pub fn validate_groups(groups: &GroupsToml, catalog: Option<&HashMap<String, i32>>) -> Vec<String> {
+  let mut problems = Vec::new();
+
+  for (group_name, champions) in &groups.groups {
+    if !is_usable_group_name(group_name) {
+      problems.push(format!("group name {:?} can't be used as a file name", group_name));
+    }
+
+    if champions.is_empty() {
+      problems.push(format!("group {} has no champions", group_name));
+    }
+
+    if let Some(catalog) = catalog {
+      for champion in champions {
+        if !catalog.contains_key(&normalize_champion_name(champion)) {
+          problems.push(format!("unknown champion {:?} in group {}", champion, group_name));
+        }
+      }
+    }
+  }
+
+  problems
+}
+
+/// Group configs are named after their group
+fn is_usable_group_name(group_name: &str) -> bool {
+  !group_name.is_empty() && !group_name.contains(|c: char| "/\\:*?\"<>|.".contains(c))
+}
+
+/// Copies the included default groups.toml to the .dark-binding config directory
+fn create_default_groups_toml(path: &Path) -> Result<()> {
+  ensure_dir(path)?;
+
+  Ok(fs::OpenOptions::new().write(true).create(true).open(path.join("groups.toml"))?.write_all(DEFAULT_GROUPS_TOML)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+  use tempdir::TempDir;
+
+  use super::*;
+
+  fn groups(entries: &[(&str, &[&str])]) -> GroupsToml {
+    let mut groups = BTreeMap::new();
+
+    for &(name, champions) in entries {
+      groups.insert(name.to_owned(), champions.iter().map(|c| c.to_string()).collect());
+    }
+
+    GroupsToml { groups: groups }
+  }
+
+  #[test]
+  /// Unknown names, empty groups and unusable group names are all reported
+  fn validates_groups() {
+    let mut catalog = HashMap::new();
+    catalog.insert("JINX".to_owned(), 222);
+    catalog.insert("KOGMAW".to_owned(), 96);
+
+    assert!(validate_groups(&groups(&[("adc", &["Jinx", "Kog'Maw"])]), Some(&catalog)).is_empty());
+
+    let problems = validate_groups(
+      &groups(&[("adc", &["Jinx", "Jihn"]), ("bot/lane", &["jinx"]), ("top", &[])]),
+      Some(&catalog)
+    );
+
+    assert_eq!(problems.len(), 3);
+  }
+
+  #[test]
+  /// Loading keeps the usable part of groups.toml and reports the rest
+  fn loads_usable_groups() {
+    let dir = TempDir::new("dark-binding-config").unwrap();
+    let config = ConfigFolder::new(dir.path().to_owned());
+    let mut catalog = HashMap::new();
+    catalog.insert("JINX".to_owned(), 222);
+    catalog.insert("ANNIE".to_owned(), 1);
+
+    fs::create_dir_all(config.profile_dir()).unwrap();
+    fs::write(
+      config.groups_toml(),
+      "[groups]\nadc = [\"Jinx\", \"Jihn\"]\n\"bot/lane\" = [\"Jinx\"]\nmages = [\"Annie\"]\n"
+    ).unwrap();
+
+    let (usable, problems) = config.usable_groups(Some(&catalog)).unwrap();
+
+    assert_eq!(usable, groups(&[("adc", &["Jinx"]), ("mages", &["Annie"])]));
+    assert_eq!(problems.len(), 2);
+    assert!(config.valid_groups(Some(&catalog)).is_err());
+  }
+
+  #[test]
+  /// A restore scheduled while quitting during a game happens on the next launch
+  fn restores_scheduled_restore() {
+    let dir = TempDir::new("dark-binding-config").unwrap();
+    let config = ConfigFolder::new(dir.path().to_owned());
+
+    fs::write(config.persisted_settings(), r#"{ "description": "base", "files": [] }"#).unwrap();
+
+    config.link_group("adc").unwrap();
+    config.schedule_restore().unwrap();
+
+    assert_eq!(config.linked_group(), Some("adc".to_owned()));
+
+    assert!(config.restore_pending().unwrap());
+    assert_eq!(config.linked_group(), None);
+    assert_eq!(read_settings_json(&config.persisted_settings()).unwrap().description, "base");
+    assert!(!config.restore_pending().unwrap());
+  }
+
+  #[test]
+  /// A new profile starts with the shared groups and configs, then keeps its own
+  fn seeds_new_profile() {
+    let dir = TempDir::new("dark-binding-config").unwrap();
+    let config = ConfigFolder::new(dir.path().to_owned());
+
+    fs::write(config.persisted_settings(), r#"{ "description": "base", "files": [] }"#).unwrap();
+    config.save_groups(&groups(&[("adc", &["Jinx"])]), None).unwrap();
+    config.link_group("adc").unwrap();
+    config.restore().unwrap();
+
+    let profile = profile_name("EUW1", "1234-abcd");
+    config.use_profile(Some(&profile[..])).unwrap();
+
+    assert_eq!(profile, "euw1-1234-abcd");
+    assert_eq!(config.group_config("adc"), dir.path().join(".dark-binding/profiles/euw1-1234-abcd/adc.json"));
+    assert!(config.group_config("adc").exists());
+
+    config.save_groups(&groups(&[("mages", &["Lux"])]), None).unwrap();
+    config.use_profile(None).unwrap();
+
+    assert!(config.groups().unwrap().groups.contains_key("adc"));
+  }
+}
@@ -0,0 +1,374 @@
+//! Local control endpoint, lets scripts and headless setups drive a running instance like the tray does.
+//!
+//! Requests are JSON objects sent one per line, like `{"command":"backup"}`, each answered with a
+//! JSON line `{"ok":true}`, `{"ok":true,"status":{..}}` or `{"ok":false,"error":".."}`. A line that
+//! isn't JSON is answered with an error and ends the connection.
+//!
+//! On unix the endpoint is the `control.sock` Unix socket, only reachable by the user. Elsewhere it
+//! is a loopback TCP port written to `control.port`, which any local process can connect to, so
+//! every request must also carry the random token of this run, written to `control.token`, as its
+//! `token` field.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use rand::{thread_rng, Rng};
+use serde_json::{self, Value};
+use websocket::futures::sync::mpsc::UnboundedSender;
+
+use errors::*;
+use league_client::client::LeagueClientFn;
+use league_client::status::{SharedStatus, Status};
+
+#[cfg(unix)]
+const ENDPOINT_FILE: &'static str = "control.sock";
+#[cfg(not(unix))]
+const ENDPOINT_FILE: &'static str = "control.port";
+const TOKEN_FILE: &'static str = "control.token";
+
+#[serde(tag = "command", rename_all = "snake_case")]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Request {
+  Backup,
+  Restore,
+  Apply { group: String },
+  Reset { group: String },
+  Reload,
+  ReloadSettings,
+  Quit,
+  Status,
+  /// Sent by a second launch in place of starting another instance
+  Launch { args: Vec<String> }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Response {
+  pub ok: bool,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub status: Option<Status>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>
+}
+
+impl Response {
+  fn ok(status: Option<Status>) -> Response {
+    Response {
+      ok: true,
+      status: status,
+      error: None
+    }
+  }
+
+  fn error(error: String) -> Response {
+    Response {
+      ok: false,
+      status: None,
+      error: Some(error)
+    }
+  }
+}
+
+/// A listening control endpoint, its files are removed when dropped
+pub struct ControlServer {
+  endpoint: PathBuf,
+  token_file: Option<PathBuf>
+}
+
+impl Drop for ControlServer {
+  fn drop(&mut self) {
+    fs::remove_file(&self.endpoint).ok();
+
+    if let Some(ref token_file) = self.token_file {
+      fs::remove_file(token_file).ok();
+    }
+  }
+}
+
+/// Starts listening in `dir` (the `.dark-binding` folder), forwarding commands to `sender` and
+/// answering status requests from `status`
+pub fn listen(dir: &Path, sender: UnboundedSender<LeagueClientFn>, status: SharedStatus) -> Result<ControlServer> {
+  let endpoint = dir.join(ENDPOINT_FILE);
+  let token_file = match cfg!(unix) {
+    true => None,
+    false => Some(dir.join(TOKEN_FILE))
+  };
+
+  let token = match token_file {
+    Some(ref path) => {
+      let token: String = thread_rng().gen_ascii_chars().take(32).collect();
+      fs::write(path, &token).chain_err(|| ErrorKind::ConfigIo("write".to_owned(), path.clone()))?;
+
+      Some(token)
+    }
+    None => None
+  };
+
+  bind(
+    &endpoint,
+    Handler {
+      sender: sender,
+      status: status,
+      token: token
+    }
+  )?;
+
+  Ok(ControlServer {
+    endpoint: endpoint,
+    token_file: token_file
+  })
+}
+
+/// Sends a single request to the instance listening in `dir` and returns its response
+pub fn send_request(dir: &Path, request: Request) -> Result<Response> {
+  let mut stream = connect(&dir.join(ENDPOINT_FILE))?;
+
+  writeln!(stream, "{}", authenticated(dir, serde_json::to_value(&request)?)?)?;
+
+  let mut answer = String::new();
+  BufReader::new(stream).read_line(&mut answer)?;
+
+  Ok(serde_json::from_str(&answer)?)
+}
+
+/// Adds the token of the instance listening in `dir` to `request`, where one is required
+fn authenticated(dir: &Path, mut request: Value) -> Result<Value> {
+  if !cfg!(unix) {
+    request["token"] = Value::String(fs::read_to_string(dir.join(TOKEN_FILE))?.trim().to_owned());
+  }
+
+  Ok(request)
+}
+
+#[derive(Clone)]
+struct Handler {
+  sender: UnboundedSender<LeagueClientFn>,
+  status: SharedStatus,
+  /// Required as the `token` field of every request, when set
+  token: Option<String>
+}
+
+impl Handler {
+  /// Reads the request of a line, an error means the connection is rejected
+  fn parse(&self, line: &str) -> Result<::std::result::Result<Request, String>> {
+    let value: Value = serde_json::from_str(line).chain_err(|| "invalid request, expected a JSON object")?;
+
+    if let Some(ref token) = self.token {
+      if value.get("token").and_then(Value::as_str) != Some(token) {
+        bail!("missing or wrong token, see control.token");
+      }
+    }
+
+    Ok(serde_json::from_value(value).map_err(|e| format!("unknown command: {}", e)))
+  }
+
+  fn handle(&self, request: Request) -> Response {
+    let command = match request {
+      Request::Status => return Response::ok(Some(self.status.snapshot())),
+      Request::Backup => LeagueClientFn::BackupConfig,
+      Request::Restore => LeagueClientFn::RestoreConfig,
+      Request::Apply { group } => LeagueClientFn::ApplyGroup(group),
+      Request::Reset { group } => LeagueClientFn::ResetGroup(group),
+      Request::Reload => LeagueClientFn::ReloadGroups,
+      Request::ReloadSettings => LeagueClientFn::ReloadSettings,
+      Request::Quit => LeagueClientFn::Shutdown,
+      Request::Launch { args } => LeagueClientFn::Launched(args)
+    };
+
+    match self.sender.unbounded_send(command) {
+      Ok(_) => Response::ok(None),
+      Err(_) => Response::error("client is shutting down".to_owned())
+    }
+  }
+
+  fn serve<S: Read + Write>(self, stream: S) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    while reader.read_line(&mut line)? > 0 {
+      let response = match self.parse(&line) {
+        Ok(Ok(request)) => self.handle(request),
+        Ok(Err(e)) => Response::error(e),
+        Err(e) => {
+          writeln!(reader.get_mut(), "{}", serde_json::to_string(&Response::error(e.to_string()))?)?;
+
+          break;
+        }
+      };
+
+      writeln!(reader.get_mut(), "{}", serde_json::to_string(&response)?)?;
+      line.clear();
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(unix)]
+fn bind(endpoint: &Path, handler: Handler) -> Result<()> {
+  use std::os::unix::net::UnixListener;
+
+  // Left behind by an instance that didn't shut down cleanly
+  if endpoint.exists() {
+    fs::remove_file(endpoint)?;
+  }
+
+  let listener = UnixListener::bind(endpoint)?;
+
+  thread::spawn(move || for stream in listener.incoming() {
+    if let Ok(stream) = stream {
+      let handler = handler.clone();
+      thread::spawn(move || handler.serve(stream));
+    }
+  });
+
+  Ok(())
+}
+
+#[cfg(not(unix))]
+fn bind(endpoint: &Path, handler: Handler) -> Result<()> {
+  use std::net::TcpListener;
+
+  let listener = TcpListener::bind("127.0.0.1:0")?;
+
+  fs::write(endpoint, listener.local_addr()?.port().to_string())?;
+
+  thread::spawn(move || for stream in listener.incoming() {
+    if let Ok(stream) = stream {
+      let handler = handler.clone();
+      thread::spawn(move || handler.serve(stream));
+    }
+  });
+
+  Ok(())
+}
+
+#[cfg(unix)]
+fn connect(endpoint: &Path) -> Result<::std::os::unix::net::UnixStream> {
+  Ok(::std::os::unix::net::UnixStream::connect(endpoint)?)
+}
+
+#[cfg(not(unix))]
+fn connect(endpoint: &Path) -> Result<::std::net::TcpStream> {
+  let port: u16 = fs::read_to_string(endpoint)?.trim().parse()?;
+
+  Ok(::std::net::TcpStream::connect(("127.0.0.1", port))?)
+}
+
+#[cfg(test)]
+mod tests {
+  use tempdir::TempDir;
+  use websocket::futures::Stream;
+  use websocket::futures::sync::mpsc::unbounded;
+
+  use league_client::status::ConnectionState;
+  use league_client::util::symlink_file;
+  use super::*;
+
+  #[test]
+  /// Commands sent to the endpoint reach the client channel
+  fn forwards_commands() {
+    let dir = TempDir::new("dark-binding-control").unwrap();
+    let (sender, rx) = unbounded();
+    let _server = listen(dir.path(), sender, SharedStatus::new(dir.path().to_owned())).unwrap();
+
+    assert!(send_request(dir.path(), Request::Backup).unwrap().ok);
+
+    match rx.wait().next() {
+      Some(Ok(LeagueClientFn::BackupConfig)) => {}
+      _ => panic!("expected a backup command")
+    }
+  }
+
+  /// Sends `line` then a backup request on one connection, returns every response
+  fn send_line(dir: &Path, line: &str) -> Vec<Response> {
+    let mut stream = connect(&dir.join(ENDPOINT_FILE)).unwrap();
+    let backup = authenticated(dir, serde_json::to_value(&Request::Backup).unwrap()).unwrap();
+
+    writeln!(stream, "{}", line).unwrap();
+    writeln!(stream, "{}", backup).unwrap();
+    stream.shutdown(::std::net::Shutdown::Write).unwrap();
+
+    BufReader::new(stream)
+      .lines()
+      .map(|l| serde_json::from_str(&l.unwrap()).unwrap())
+      .collect()
+  }
+
+  #[test]
+  /// Unknown commands are answered with an error, the connection stays usable
+  fn rejects_unknown_commands() {
+    let dir = TempDir::new("dark-binding-control").unwrap();
+    let (sender, _rx) = unbounded();
+    let _server = listen(dir.path(), sender, SharedStatus::new(dir.path().to_owned())).unwrap();
+
+    let unknown = authenticated(dir.path(), json!({ "command": "launch_missiles" })).unwrap();
+    let responses = send_line(dir.path(), &unknown.to_string());
+
+    assert_eq!(responses.len(), 2);
+    assert!(!responses[0].ok);
+    assert!(responses[0].error.as_ref().unwrap().starts_with("unknown command"));
+    assert!(responses[1].ok);
+  }
+
+  #[test]
+  /// A line that isn't JSON ends the connection before any command is forwarded
+  fn rejects_invalid_requests() {
+    let dir = TempDir::new("dark-binding-control").unwrap();
+    let (sender, rx) = unbounded();
+    let _server = listen(dir.path(), sender, SharedStatus::new(dir.path().to_owned())).unwrap();
+
+    let responses = send_line(dir.path(), "backup");
+
+    assert_eq!(responses.len(), 1);
+    assert!(!responses[0].ok);
+
+    // The first command to arrive is the one sent after the rejected connection
+    assert!(send_request(dir.path(), Request::Reload).unwrap().ok);
+
+    match rx.wait().next() {
+      Some(Ok(LeagueClientFn::ReloadGroups)) => {}
+      _ => panic!("expected only the reload command")
+    }
+  }
+
+  #[test]
+  /// With a token, requests without it are rejected
+  fn requires_token() {
+    let (sender, _rx) = unbounded();
+    let handler = Handler {
+      sender: sender,
+      status: SharedStatus::new(PathBuf::from("config")),
+      token: Some("secret".to_owned())
+    };
+
+    assert!(handler.parse(r#"{"command":"backup"}"#).is_err());
+    assert!(handler.parse(r#"{"command":"backup","token":"guess"}"#).is_err());
+    assert_eq!(
+      handler.parse(r#"{"command":"backup","token":"secret"}"#).unwrap(),
+      Ok(Request::Backup)
+    );
+  }
+
+  #[test]
+  /// Status requests report the connection and the linked group
+  fn reports_status() {
+    let dir = TempDir::new("dark-binding-control").unwrap();
+    let control_dir = dir.path().join(".dark-binding");
+    let group_config = control_dir.join("adc.json");
+    let status = SharedStatus::new(dir.path().to_owned());
+    let (sender, _rx) = unbounded();
+
+    fs::create_dir(&control_dir).unwrap();
+    fs::write(&group_config, "{}").unwrap();
+    symlink_file(&group_config, &dir.path().join("PersistedSettings.json")).unwrap();
+    status.set_connection(ConnectionState::Connected);
+
+    let _server = listen(&control_dir, sender, status).unwrap();
+    let status = send_request(&control_dir, Request::Status).unwrap().status.unwrap();
+
+    assert_eq!(status.connection, ConnectionState::Connected);
+    assert_eq!(status.group, Some("adc".to_owned()));
+    assert_eq!(status.linked_file, Some(group_config));
+  }
+}
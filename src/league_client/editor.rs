@@ -0,0 +1,118 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use errors::*;
+
+/// Opens `path` in an editor, without waiting for it to exit
+///
+/// Uses the configured `editor` command, then `$VISUAL` and `$EDITOR`, and
+/// otherwise whatever application the OS opens the file with.
+pub fn open(editor: Option<&str>, path: &Path) -> Result<()> {
+  let editor = editor
+    .map(str::to_owned)
+    .or_else(|| env::var("VISUAL").ok())
+    .or_else(|| env::var("EDITOR").ok())
+    .filter(|e| !e.trim().is_empty());
+
+  let mut command = match editor {
+    Some(ref editor) => {
+      let words = command_words(editor)?;
+      let mut command = Command::new(&words[0]);
+      command.args(&words[1..]);
+
+      command
+    }
+    None => default_opener()
+  };
+
+  command
+    .arg(path)
+    .spawn()
+    .chain_err(|| format!("unable to open {:?}", path))?;
+
+  Ok(())
+}
+
+/// Splits an editor command into the program and its arguments
+///
+/// Words are separated by whitespace, single or double quotes keep a path with
+/// spaces in one word. Backslashes are kept as is for Windows paths. A setting
+/// naming an existing file is that program, quoted or not.
+fn command_words(editor: &str) -> Result<Vec<String>> {
+  if Path::new(editor.trim()).is_file() {
+    return Ok(vec![editor.trim().to_owned()]);
+  }
+
+  let mut words = Vec::new();
+  let mut word: Option<String> = None;
+  let mut quote = None;
+
+  for c in editor.chars() {
+    match (quote, c) {
+      (Some(q), c) if c == q => quote = None,
+      (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+      (None, '"') | (None, '\'') => {
+        quote = Some(c);
+        word.get_or_insert_with(String::new);
+      }
+      (None, c) if c.is_whitespace() => words.extend(word.take()),
+      (None, c) => word.get_or_insert_with(String::new).push(c)
+    }
+  }
+
+  if quote.is_some() {
+    bail!("unclosed quote in editor command {:?}", editor);
+  }
+
+  words.extend(word);
+
+  Ok(words)
+}
+
+/// Opens a file or URL with the application the OS associates with it
+pub fn open_default(target: &str) -> Result<()> {
+  default_opener()
+    .arg(target)
+    .spawn()
+    .chain_err(|| format!("unable to open {}", target))?;
+
+  Ok(())
+}
+
+#[cfg(windows)]
+fn default_opener() -> Command {
+  let mut command = Command::new("cmd");
+  // The empty title keeps `start` from taking a quoted path as the window title
+  command.args(&["/C", "start", ""]);
+
+  command
+}
+
+#[cfg(target_os = "macos")]
+fn default_opener() -> Command {
+  Command::new("open")
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn default_opener() -> Command {
+  Command::new("xdg-open")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  /// Quoted paths with spaces stay one word, backslashes are kept
+  fn splits_editor_commands() {
+    assert_eq!(command_words("code --wait").unwrap(), vec!["code", "--wait"]);
+    assert_eq!(
+      command_words(r#""C:\Program Files\Notepad++\notepad++.exe" -multiInst"#).unwrap(),
+      vec![r"C:\Program Files\Notepad++\notepad++.exe", "-multiInst"]
+    );
+    assert_eq!(command_words("vim -c 'set ft=toml'").unwrap(), vec!["vim", "-c", "set ft=toml"]);
+    assert_eq!(command_words("  ").unwrap(), Vec::<String>::new());
+    assert!(command_words("\"/opt/my editor").is_err());
+  }
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde_json::Value;
+
+use errors::*;
+
+use league_client::structs::PersistedSettings;
+use league_client::util::{ensure_dir, symlink_file, write_settings_json};
+
+/// Filesystem operations of the config swap
+///
+/// In dry-run mode every operation is logged instead of performed, so the
+/// swap logic can be followed without touching any file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileOps {
+  pub dry_run: bool
+}
+
+impl FileOps {
+  pub fn new(dry_run: bool) -> FileOps {
+    FileOps { dry_run: dry_run }
+  }
+
+  fn plan(&self, operation: &str) -> bool {
+    if self.dry_run {
+      info!("[dry-run] {}", operation);
+    }
+
+    self.dry_run
+  }
+
+  pub fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+    if !self.plan(&format!("copy {:?} to {:?}", from, to)) {
+      fs::copy(from, to).chain_err(|| ErrorKind::ConfigIo(format!("copy {:?} to", from), to.to_owned()))?;
+    }
+
+    Ok(())
+  }
+
+  pub fn remove(&self, path: &Path) -> Result<()> {
+    if !self.plan(&format!("remove {:?}", path)) {
+      fs::remove_file(path).chain_err(|| ErrorKind::ConfigIo("remove".to_owned(), path.to_owned()))?;
+    }
+
+    Ok(())
+  }
+
+  pub fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+    if !self.plan(&format!("move {:?} to {:?}", from, to)) {
+      fs::rename(from, to).chain_err(|| ErrorKind::ConfigIo(format!("move {:?} to", from), to.to_owned()))?;
+    }
+
+    Ok(())
+  }
+
+  pub fn link(&self, src: &Path, dst: &Path) -> Result<()> {
+    if self.plan(&format!("link {:?} to {:?}", dst, src)) {
+      return Ok(());
+    }
+
+    if let Err(e) = symlink_file(src, dst) {
+      let denied = match *e.kind() {
+        ErrorKind::Io(ref e) => is_permission_denied(e),
+        _ => false
+      };
+
+      let kind = if denied {
+        ErrorKind::LinkPermission(dst.to_owned())
+      } else {
+        ErrorKind::ConfigIo(format!("link {:?} to", src), dst.to_owned())
+      };
+
+      return Err(Error::with_chain(e, kind));
+    }
+
+    Ok(())
+  }
+
+  pub fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+    if !self.plan(&format!("write {:?} ({} bytes)", path, contents.len())) {
+      fs::write(path, contents).chain_err(|| ErrorKind::ConfigIo("write".to_owned(), path.to_owned()))?;
+    }
+
+    Ok(())
+  }
+
+  pub fn ensure_dir(&self, path: &Path) -> Result<()> {
+    if !path.exists() && self.plan(&format!("create directory {:?}", path)) {
+      return Ok(());
+    }
+
+    ensure_dir(path)
+  }
+
+  /// Writes `settings` to `path`, listing every setting that changes
+  pub fn write_settings(&self, path: &Path, previous: &PersistedSettings, settings: &PersistedSettings) -> Result<()> {
+    let changes = diff_settings(previous, settings);
+
+    if self.plan(&format!("merge into {:?} ({} changes)", path, changes.len())) {
+      for change in &changes {
+        info!("[dry-run]   {}", change);
+      }
+
+      return Ok(());
+    }
+
+    for change in &changes {
+      debug!("  {}", change);
+    }
+
+    write_settings_json(path, settings)
+  }
+}
+
+/// Flattens settings files into `file/section/setting -> value`
+fn flatten(settings: &PersistedSettings) -> BTreeMap<String, Value> {
+  let mut flat = BTreeMap::new();
+
+  for file in &settings.files {
+    match file.sections {
+      Value::Array(ref sections) => {
+        for section in sections {
+          let section_name = section["name"].as_str().unwrap_or("");
+
+          match section["settings"] {
+            Value::Array(ref entries) => {
+              for entry in entries {
+                let key = format!("{}/{}/{}", file.name, section_name, entry["name"].as_str().unwrap_or(""));
+                flat.insert(key, entry["value"].clone());
+              }
+            }
+            ref other => {
+              flat.insert(format!("{}/{}", file.name, section_name), other.clone());
+            }
+          }
+        }
+      }
+      ref other => {
+        flat.insert(file.name.clone(), other.clone());
+      }
+    }
+  }
+
+  flat
+}
+
+/// Human readable per-key differences between two settings
+pub fn diff_settings(old: &PersistedSettings, new: &PersistedSettings) -> Vec<String> {
+  let old = flatten(old);
+  let new = flatten(new);
+  let mut changes = Vec::new();
+
+  for (key, value) in &new {
+    match old.get(key) {
+      None => changes.push(format!("+ {} = {}", key, value)),
+      Some(previous) if previous != value => changes.push(format!("~ {}: {} -> {}", key, previous, value)),
+      _ => {}
+    }
+  }
+
+  for (key, value) in &old {
+    if !new.contains_key(key) {
+      changes.push(format!("- {} = {}", key, value));
+    }
+  }
+
+  changes
+}
+
+/// Windows reports a missing symlink privilege as ERROR_PRIVILEGE_NOT_HELD
+fn is_permission_denied(e: &io::Error) -> bool {
+  e.kind() == io::ErrorKind::PermissionDenied || (cfg!(windows) && e.raw_os_error() == Some(1314))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::File;
+  use std::io::Write;
+  use serde_json;
+  use tempdir::TempDir;
+
+  use super::*;
+
+  fn settings(value: Value) -> PersistedSettings {
+    serde_json::from_value(value).unwrap()
+  }
+
+  fn game_cfg(volume: f64, colorblind: Option<bool>) -> PersistedSettings {
+    let mut entries = vec![json!({ "name": "MasterVolume", "value": volume })];
+
+    if let Some(colorblind) = colorblind {
+      entries.push(json!({ "name": "ColorblindMode", "value": colorblind }));
+    }
+
+    settings(json!({
+      "description": "",
+      "files": [{ "name": "Game.cfg", "sections": [{ "name": "General", "settings": entries }] }]
+    }))
+  }
+
+  #[test]
+  /// A dry run plans every operation without touching the filesystem
+  fn dry_run_touches_nothing() {
+    let dir = TempDir::new("dark-binding-ops").unwrap();
+    let file = dir.path().join("PersistedSettings.json");
+    let other = dir.path().join("adc.json");
+    let ops = FileOps::new(true);
+
+    File::create(&file).unwrap().write_all(b"base").unwrap();
+
+    ops.copy(&file, &other).unwrap();
+    ops.rename(&file, &other).unwrap();
+    ops.link(&file, &other).unwrap();
+    ops.write_file(&other, b"adc").unwrap();
+    ops.ensure_dir(&dir.path().join("profiles")).unwrap();
+    ops.write_settings(&other, &game_cfg(0.5, None), &game_cfg(1.0, None)).unwrap();
+    ops.remove(&file).unwrap();
+
+    assert_eq!(fs::read(&file).unwrap(), b"base");
+    assert!(fs::symlink_metadata(&other).is_err());
+    assert!(!dir.path().join("profiles").exists());
+  }
+
+  #[test]
+  /// Without a dry run the operations are performed
+  fn performs_operations() {
+    let dir = TempDir::new("dark-binding-ops").unwrap();
+    let file = dir.path().join("PersistedSettings.json");
+    let copy = dir.path().join("PersistedSettings.bak");
+    let ops = FileOps::new(false);
+
+    ops.write_file(&file, b"base").unwrap();
+    ops.copy(&file, &copy).unwrap();
+    ops.remove(&file).unwrap();
+
+    assert!(!file.exists());
+    assert_eq!(fs::read(&copy).unwrap(), b"base");
+  }
+
+  #[test]
+  /// Added, changed and removed settings are each listed once
+  fn diffs_settings() {
+    let changes = diff_settings(&game_cfg(0.5, Some(true)), &game_cfg(1.0, None));
+
+    assert_eq!(
+      changes,
+      vec![
+        "~ Game.cfg/General/MasterVolume: 0.5 -> 1.0".to_owned(),
+        "- Game.cfg/General/ColorblindMode = true".to_owned()
+      ]
+    );
+
+    let changes = diff_settings(&game_cfg(0.5, None), &game_cfg(0.5, Some(false)));
+
+    assert_eq!(changes, vec!["+ Game.cfg/General/ColorblindMode = false".to_owned()]);
+    assert!(diff_settings(&game_cfg(0.5, None), &game_cfg(0.5, None)).is_empty());
+  }
+}
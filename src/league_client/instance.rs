@@ -0,0 +1,176 @@
+//! Keeps a single instance per config folder
+//!
+//! The first instance holds `instance.lock` in the `.dark-binding` folder. A later
+//! launch finding the lock with a running instance behind it hands its arguments
+//! over through the control endpoint instead of swapping the same symlink.
+//! The running instance only logs them, it keeps the options it started with.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind as IoErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use errors::*;
+use league_client::control::{send_request, Request};
+
+const LOCK_FILE: &'static str = "instance.lock";
+
+/// How long a lock without a control endpoint is given to belong to an instance still starting
+const STARTUP_ATTEMPTS: u32 = 6;
+
+/// Held by the running instance, the lock file is removed when dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+  path: PathBuf
+}
+
+impl InstanceLock {
+  /// Whether this is the lock of the `.dark-binding` folder `dir`
+  pub fn is_in(&self, dir: &Path) -> bool {
+    self.path.parent() == Some(dir)
+  }
+}
+
+impl Drop for InstanceLock {
+  fn drop(&mut self) {
+    fs::remove_file(&self.path).ok();
+  }
+}
+
+/// Whether an instance answers on the control endpoint in `dir`
+pub fn running(dir: &Path) -> bool {
+  send_request(dir, Request::Status).is_ok()
+}
+
+/// PID written in the lock file, `None` until its instance has written it
+fn holder(path: &Path) -> Option<u32> {
+  fs::read_to_string(path).ok().and_then(|pid| pid.trim().parse().ok())
+}
+
+#[cfg(unix)]
+fn process_running(pid: u32) -> bool {
+  Command::new("kill")
+    .args(&["-0", &pid.to_string()])
+    .stderr(Stdio::null())
+    .status()
+    .map(|status| status.success())
+    .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn process_running(pid: u32) -> bool {
+  let filter = format!("PID eq {}", pid);
+
+  Command::new("tasklist")
+    .args(&["/FI", &filter, "/FO", "CSV", "/NH"])
+    .stderr(Stdio::null())
+    .output()
+    .map(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+    .unwrap_or(false)
+}
+
+/// Takes the lock in `dir` (the `.dark-binding` folder), `None` if another
+/// instance holds it
+///
+/// A lock whose process is gone is left over from a crash and taken over. A
+/// lock whose process is alive but never starts answering isn't, in case it's
+/// an instance whose control endpoint broke.
+pub fn lock(dir: &Path) -> Result<Option<InstanceLock>> {
+  let path = dir.join(LOCK_FILE);
+
+  for attempt in 0..STARTUP_ATTEMPTS + 1 {
+    match OpenOptions::new().write(true).create_new(true).open(&path) {
+      Ok(mut file) => {
+        write!(file, "{}", process::id())?;
+
+        return Ok(Some(InstanceLock { path: path }));
+      }
+      Err(ref e) if e.kind() == IoErrorKind::AlreadyExists => {}
+      Err(e) => return Err(Error::with_chain(e, ErrorKind::ConfigIo("lock".to_owned(), path)))
+    }
+
+    if running(dir) {
+      return Ok(None);
+    }
+
+    match holder(&path) {
+      Some(pid) if !process_running(pid) => break,
+      _ => {}
+    }
+
+    if attempt < STARTUP_ATTEMPTS {
+      thread::sleep(Duration::from_millis(500));
+    }
+  }
+
+  if let Some(pid) = holder(&path) {
+    if process_running(pid) {
+      bail!(
+        "{} is held by process {}, which doesn't answer on the control endpoint",
+        path.display(),
+        pid
+      );
+    }
+  }
+
+  info!("op=lock path={:?} outcome=taken_over", path);
+
+  fs::remove_file(&path).chain_err(|| ErrorKind::ConfigIo("remove stale lock".to_owned(), path.clone()))?;
+
+  lock(dir)
+}
+
+/// Hands this launch's arguments to the instance running in `dir`
+pub fn hand_off(dir: &Path) -> Result<()> {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let response = send_request(dir, Request::Launch { args: args })?;
+
+  if !response.ok {
+    bail!(response.error.unwrap_or_default());
+  }
+
+  println!("dark-binding is already running, arguments handed over to it");
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use tempdir::TempDir;
+
+  use super::*;
+
+  #[test]
+  /// A lock left behind without a running instance is taken over, and released when dropped
+  fn takes_over_stale_lock() {
+    let dir = TempDir::new("dark-binding-instance").unwrap();
+    let path = dir.path().join(LOCK_FILE);
+
+    // Above the largest PID Linux hands out, and odd where Windows' are multiples of 4
+    fs::write(&path, "4194305").unwrap();
+
+    let lock = lock(dir.path()).unwrap().unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), process::id().to_string());
+
+    drop(lock);
+
+    assert!(!path.exists());
+  }
+
+  #[test]
+  /// A lock held by a live process isn't taken over, even if it doesn't answer
+  fn keeps_lock_of_live_process() {
+    let dir = TempDir::new("dark-binding-instance").unwrap();
+    let path = dir.path().join(LOCK_FILE);
+    let pid = process::id().to_string();
+
+    fs::write(&path, &pid).unwrap();
+
+    assert!(lock(dir.path()).is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), pid);
+  }
+}
@@ -0,0 +1,178 @@
+use std::borrow::Borrow;
+use reqwest::{Client, Method, Response, Url};
+use serde::ser::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use HTTP_CLIENT;
+use errors::*;
+
+use league_client::client::Credentials;
+use league_client::retry::RetryPolicy;
+use league_client::structs::*;
+use league_client::structs::Error as LcuError;
+
+/// Typed REST client for the League Client Update API
+#[derive(Clone)]
+pub struct LcuClient {
+  credentials: Credentials,
+  http: Client,
+  retry: RetryPolicy
+}
+
+impl LcuClient {
+  pub fn new(credentials: Credentials) -> LcuClient {
+    LcuClient {
+      credentials: credentials,
+      http: HTTP_CLIENT.clone(),
+      retry: RetryPolicy::default()
+    }
+  }
+
+  /// Sends requests through `http` instead of the shared client
+  pub fn with_http_client(mut self, http: Client) -> LcuClient {
+    self.http = http;
+
+    self
+  }
+
+  /// Uses `retry` for every request made through this client
+  pub fn with_retry(mut self, retry: RetryPolicy) -> LcuClient {
+    self.retry = retry;
+
+    self
+  }
+
+  pub fn build_uri<I, K, V>(&self, endpoint: &str, query: Option<I>) -> Result<Url>
+    where I: IntoIterator,
+          I::Item: Borrow<(K, V)>,
+          K: AsRef<str>,
+          V: AsRef<str> {
+    let uri = match endpoint.starts_with("/") {
+      true => format!("https://127.0.0.1:{}{}", self.credentials.port, endpoint),
+      false => format!("https://127.0.0.1:{}/{}", self.credentials.port, endpoint)
+    };
+
+    if let Some(opts) = query {
+      return Ok(Url::parse_with_params(&uri, opts)?);
+    }
+
+    Ok(Url::parse(&uri)?)
+  }
+
+  /// Sends a request according to the retry policy
+  pub fn send<B: Serialize>(&self, method: Method, uri: Url, body: Option<&B>) -> Result<Response> {
+    let idempotent = is_idempotent(&method);
+
+    self
+      .retry
+      .run(idempotent, || self.send_once(method.clone(), uri.clone(), body))
+  }
+
+  /// Sends a request and maps non-success responses into `ErrorKind::LcuApi`
+  fn send_once<B: Serialize>(&self, method: Method, uri: Url, body: Option<&B>) -> Result<Response> {
+    let mut request = self.http.request(method, uri);
+
+    request.basic_auth("riot", Some(self.credentials.token.to_owned()));
+
+    if let Some(json) = body {
+      request.json(json);
+    }
+
+    let mut res = request.send()?;
+
+    if res.status().is_success() {
+      return Ok(res);
+    }
+
+    let status = res.status().as_u16();
+    let text = res.text().unwrap_or_default();
+
+    Err(
+      match serde_json::from_str::<LcuError>(&text) {
+        Ok(e) => ErrorKind::LcuApi(status, e.error_code, e.message).into(),
+        Err(_) => ErrorKind::LcuApi(status, String::new(), text).into()
+      }
+    )
+  }
+
+  fn request<B: Serialize, T: DeserializeOwned>(&self, method: Method, endpoint: &str, body: Option<&B>) -> Result<T> {
+    let uri = self.build_uri(endpoint, None::<&[(String, String)]>)?;
+
+    read_json(self.send(method, uri, body)?)
+  }
+
+  pub fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+    self.request(Method::Get, endpoint, None::<&()>)
+  }
+
+  pub fn get_with_query<T, I, K, V>(&self, endpoint: &str, query: I) -> Result<T>
+    where T: DeserializeOwned,
+          I: IntoIterator,
+          I::Item: Borrow<(K, V)>,
+          K: AsRef<str>,
+          V: AsRef<str> {
+    let uri = self.build_uri(endpoint, Some(query))?;
+
+    read_json(self.send(Method::Get, uri, None::<&()>)?)
+  }
+
+  pub fn post<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> Result<T> {
+    self.request(Method::Post, endpoint, Some(body))
+  }
+
+  pub fn put<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> Result<T> {
+    self.request(Method::Put, endpoint, Some(body))
+  }
+
+  pub fn patch<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> Result<T> {
+    self.request(Method::Patch, endpoint, Some(body))
+  }
+
+  pub fn delete<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+    self.request(Method::Delete, endpoint, None::<&()>)
+  }
+
+  pub fn current_summoner(&self) -> Result<LocalSummoner> {
+    self.get("/lol-summoner/v1/current-summoner")
+  }
+
+  pub fn rso_authorization(&self) -> Result<RSO> {
+    self.get("/rso-auth/v1/authorization")
+  }
+
+  pub fn champions_minimal(&self, summoner_id: &str) -> Result<Vec<ChampionMinimal>> {
+    self.get(&format!("/lol-champions/v1/inventories/{}/champions-minimal", summoner_id))
+  }
+
+  pub fn gameflow_phase(&self) -> Result<GameflowPhase> {
+    self.get("/lol-gameflow/v1/gameflow-phase")
+  }
+
+  pub fn gameflow_session(&self) -> Result<GameflowSession> {
+    self.get("/lol-gameflow/v1/session")
+  }
+
+  pub fn champ_select_session(&self) -> Result<ChampionSelectSessionUpdate> {
+    self.get("/lol-champ-select/v1/session")
+  }
+}
+
+/// Deserializes a response body, empty bodies (e.g. 204) are read as `null`
+fn read_json<T: DeserializeOwned>(mut res: Response) -> Result<T> {
+  let text = res.text()?;
+
+  match text.trim().is_empty() {
+    true => Ok(serde_json::from_str("null")?),
+    false => Ok(serde_json::from_str(&text)?)
+  }
+}
+
+/// Requests the LCU can be sent twice without a different outcome, DELETE
+/// endpoints of the LCU often act on the current state (e.g. leave a lobby)
+fn is_idempotent(method: &Method) -> bool {
+  match *method {
+    Method::Get | Method::Head | Method::Options | Method::Put => true,
+    _ => false
+  }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+use reqwest::{Certificate, Client};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use CERTIFICATE;
+use errors::*;
+
+use league_client::structs::LivePlayer;
+
+static LIVE_CLIENT_URL: &'static str = "https://127.0.0.1:2999/liveclientdata";
+
+lazy_static! {
+  // Called from the event loop, a game that doesn't answer mustn't hold it up
+  static ref LIVE_CLIENT: Client = Client::builder()
+    .add_root_certificate(Certificate::from_der(CERTIFICATE).unwrap())
+    .timeout(Duration::from_millis(500))
+    .build()
+    .unwrap();
+}
+
+fn get_json<T: DeserializeOwned>(endpoint: &str) -> Result<T> {
+  Ok(
+    LIVE_CLIENT
+      .get(&format!("{}{}", LIVE_CLIENT_URL, endpoint))
+      .send()?
+      .error_for_status()?
+      .json()?
+  )
+}
+
+/// Whether a game is running, its process serves the live client API
+pub fn game_running() -> bool {
+  get_json::<Value>("/activeplayername").is_ok()
+}
+
+/// Name of the champion played by the local player in the running game
+///
+/// Only available once the game process is up, used for modes that never go
+/// through a champ select session (e.g. practice tool champion swaps)
+pub fn active_player_champion() -> Result<Option<String>> {
+  let active_player = match get_json::<Value>("/activeplayername")? {
+    Value::String(name) => name,
+    _ => return Ok(None)
+  };
+
+  let players: Vec<LivePlayer> = get_json("/playerlist")?;
+
+  Ok(
+    players
+      .into_iter()
+      .find(|p| p.summoner_name == active_player)
+      .map(|p| p.champion_name)
+  )
+}
@@ -0,0 +1,221 @@
+//! Where League is installed and which Config folder to swap
+//!
+//! The running client only tells us its install directory, which isn't enough for
+//! custom installs, PBE next to live or Wine prefixes. In order of precedence the
+//! Config folder comes from:
+//!
+//! 1. `--config-dir` or `DARK_BINDING_CONFIG_DIR`
+//! 2. `--install-dir` or `DARK_BINDING_INSTALL_DIR`, joined with `Config`
+//! 3. `--installation` or `DARK_BINDING_INSTALLATION`, naming an entry of `locations.toml`
+//! 4. the `locations.toml` entry whose install directory is the running client's
+//! 5. the running client's install directory, joined with `Config`
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use toml;
+
+use errors::*;
+
+static LOCATIONS_FILE: &'static str = "locations.toml";
+
+/// A named League installation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Installation {
+  pub name: String,
+  pub install_dir: PathBuf,
+  /// Defaults to `Config` inside the install directory
+  #[serde(default)]
+  pub config_dir: Option<PathBuf>
+}
+
+impl Installation {
+  pub fn config_dir(&self) -> PathBuf {
+    self
+      .config_dir
+      .clone()
+      .unwrap_or_else(|| self.install_dir.join("Config"))
+  }
+}
+
+/// Known installations, kept in `locations.toml` in the user's config directory
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Locations {
+  /// Installation used when no client is running
+  #[serde(default)]
+  pub default: Option<String>,
+  #[serde(default)]
+  pub installations: Vec<Installation>
+}
+
+impl Locations {
+  /// Reads `locations.toml`, a missing file means no known installations
+  pub fn load() -> Result<Locations> {
+    match app_config_dir() {
+      Some(dir) => Locations::read(&dir.join(LOCATIONS_FILE)),
+      None => Ok(Locations::default())
+    }
+  }
+
+  pub fn read(path: &Path) -> Result<Locations> {
+    if !path.exists() {
+      return Ok(Locations::default());
+    }
+
+    let mut s = String::new();
+    File::open(path)?.read_to_string(&mut s)?;
+
+    Ok(toml::from_str(&s).chain_err(|| ErrorKind::Parse(format!("{:?}", path)))?)
+  }
+
+  pub fn find(&self, name: &str) -> Option<&Installation> {
+    self.installations.iter().find(|i| i.name == name)
+  }
+
+  /// Installation the running client was started from
+  pub fn find_by_install_dir(&self, install_dir: &Path) -> Option<&Installation> {
+    self
+      .installations
+      .iter()
+      .find(|i| same_path(&i.install_dir, install_dir))
+  }
+}
+
+/// Directory overrides from the command line, falling back to the environment
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryOverrides {
+  pub config_dir: Option<PathBuf>,
+  pub install_dir: Option<PathBuf>,
+  pub installation: Option<String>
+}
+
+impl DirectoryOverrides {
+  /// Fills whatever wasn't given on the command line from the environment
+  pub fn with_env(self) -> DirectoryOverrides {
+    DirectoryOverrides {
+      config_dir: self.config_dir.or_else(|| env::var_os("DARK_BINDING_CONFIG_DIR").map(PathBuf::from)),
+      install_dir: self.install_dir.or_else(|| env::var_os("DARK_BINDING_INSTALL_DIR").map(PathBuf::from)),
+      installation: self.installation.or_else(|| env::var("DARK_BINDING_INSTALLATION").ok())
+    }
+  }
+
+  /// Config folder for a client started from `client_install_dir`, or for no
+  /// client at all when it's `None`
+  pub fn resolve(&self, locations: &Locations, client_install_dir: Option<&Path>) -> Result<PathBuf> {
+    if let Some(ref dir) = self.config_dir {
+      return Ok(dir.clone());
+    }
+
+    if let Some(ref dir) = self.install_dir {
+      return Ok(dir.join("Config"));
+    }
+
+    if let Some(ref name) = self.installation {
+      return match locations.find(name) {
+        Some(installation) => Ok(installation.config_dir()),
+        None => bail!("there is no installation named {} in {}", name, LOCATIONS_FILE)
+      };
+    }
+
+    if let Some(install_dir) = client_install_dir {
+      return Ok(match locations.find_by_install_dir(install_dir) {
+        Some(installation) => installation.config_dir(),
+        None => install_dir.join("Config")
+      });
+    }
+
+    let default = locations
+      .default
+      .as_ref()
+      .and_then(|name| locations.find(name))
+      .or_else(|| if locations.installations.len() == 1 { locations.installations.first() } else { None });
+
+    match default {
+      Some(installation) => Ok(installation.config_dir()),
+      None => bail!(
+        "no Config folder, pass --config-dir or --install-dir, or add a default installation to {}",
+        LOCATIONS_FILE
+      )
+    }
+  }
+}
+
+/// Per-user directory for the application's own settings
+pub fn app_config_dir() -> Option<PathBuf> {
+  let base = if cfg!(windows) {
+    env::var_os("APPDATA").map(PathBuf::from)
+  } else if cfg!(target_os = "macos") {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+  } else {
+    env::var_os("XDG_CONFIG_HOME")
+      .map(PathBuf::from)
+      .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+  };
+
+  base.map(|dir| dir.join("dark-binding"))
+}
+
+/// Compares install directories the way the client reports them, which on Windows
+/// means ignoring case and slash direction
+fn same_path(a: &Path, b: &Path) -> bool {
+  let normalize = |p: &Path| {
+    p.to_string_lossy()
+      .replace('\\', "/")
+      .trim_right_matches('/')
+      .to_lowercase()
+  };
+
+  normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn locations() -> Locations {
+    toml::from_str(
+      r#"
+        default = "live"
+
+        [[installations]]
+        name = "live"
+        install_dir = "C:/Riot Games/League of Legends"
+
+        [[installations]]
+        name = "pbe"
+        install_dir = "C:/Riot Games/League of Legends (PBE)"
+        config_dir = "/home/me/.wine/drive_c/Riot Games/League of Legends (PBE)/Config"
+      "#
+    ).unwrap()
+  }
+
+  #[test]
+  /// The running client's install directory picks the matching installation
+  fn selects_installation_of_running_client() {
+    let overrides = DirectoryOverrides::default();
+    let config_dir = overrides
+      .resolve(&locations(), Some(Path::new("c:\\Riot Games\\League of Legends (PBE)\\")))
+      .unwrap();
+
+    assert_eq!(config_dir, PathBuf::from("/home/me/.wine/drive_c/Riot Games/League of Legends (PBE)/Config"));
+  }
+
+  #[test]
+  /// Explicit directories win over the running client and the default installation
+  fn overrides_take_precedence() {
+    let overrides = DirectoryOverrides {
+      install_dir: Some(PathBuf::from("/games/lol")),
+      ..DirectoryOverrides::default()
+    };
+
+    assert_eq!(
+      overrides.resolve(&locations(), Some(Path::new("C:/Riot Games/League of Legends"))).unwrap(),
+      PathBuf::from("/games/lol/Config")
+    );
+    assert_eq!(
+      DirectoryOverrides::default().resolve(&locations(), None).unwrap(),
+      PathBuf::from("C:/Riot Games/League of Legends/Config")
+    );
+  }
+}
@@ -0,0 +1,205 @@
+//! In-process stand-in for the League client
+//!
+//! Serves the LCU REST API and the WAMP websocket on the same TLS port of
+//! 127.0.0.1, like the real client does, using a self-signed certificate
+//! (`root.der`) that `LeagueClient::with_root_certificate` can trust.
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use native_tls::{Pkcs12, TlsAcceptor, TlsStream};
+use serde_json::Value;
+use websocket::OwnedMessage;
+use websocket::sync::server::IntoWs;
+
+use league_client::client::Credentials;
+
+pub static ROOT_CERTIFICATE: &'static [u8] = include_bytes!("./root.der");
+
+static IDENTITY: &'static [u8] = include_bytes!("./identity.p12");
+static IDENTITY_PASSWORD: &'static str = "mock";
+static TOKEN: &'static str = "mock-token";
+
+type Routes = HashMap<(String, String), (u16, Value)>;
+
+/// Scripted LCU, configure it and then `start` it
+pub struct MockLcu {
+  summoner_id: u64,
+  routes: Routes,
+  events: Vec<Value>,
+  event_delay: Duration
+}
+
+/// Handle to a running `MockLcu`
+pub struct MockLcuHandle {
+  pub port: u16,
+  requests: Arc<Mutex<Vec<String>>>
+}
+
+impl MockLcu {
+  /// Logged in as summoner 1 on EUW1 with no champions
+  pub fn new() -> MockLcu {
+    MockLcu {
+      summoner_id: 0,
+      routes: HashMap::new(),
+      events: Vec::new(),
+      event_delay: Duration::from_millis(50)
+    }.route("GET", "/rso-auth/v1/authorization", 200, json!({ "currentPlatformId": "EUW1" }))
+      .summoner(1, "tester")
+      .champions(&[])
+  }
+
+  /// Answers `method path` with `status` and a JSON body
+  pub fn route(mut self, method: &str, path: &str, status: u16, body: Value) -> MockLcu {
+    self
+      .routes
+      .insert((method.to_owned(), path.to_owned()), (status, body));
+
+    self
+  }
+
+  pub fn summoner(mut self, summoner_id: u64, internal_name: &str) -> MockLcu {
+    self.summoner_id = summoner_id;
+
+    self.route(
+      "GET",
+      "/lol-summoner/v1/current-summoner",
+      200,
+      json!({
+        "displayName": internal_name,
+        "internalName": internal_name,
+        "accountId": summoner_id,
+        "summonerId": summoner_id,
+        "summonerLevel": 30,
+        "profileIconId": 1
+      })
+    )
+  }
+
+  /// Champion inventory of the current summoner, as `(id, alias)` pairs
+  pub fn champions(self, champions: &[(i32, &str)]) -> MockLcu {
+    let path = format!("/lol-champions/v1/inventories/{}/champions-minimal", self.summoner_id);
+    let body = champions
+      .iter()
+      .map(|&(id, alias)| json!({ "id": id, "alias": alias }))
+      .collect();
+
+    self.route("GET", &path, 200, Value::Array(body))
+  }
+
+  /// Queues an `OnJsonApiEvent` sent to websocket subscribers, in order
+  pub fn event(mut self, event_type: &str, uri: &str, data: Value) -> MockLcu {
+    self
+      .events
+      .push(json!([8, "OnJsonApiEvent", { "eventType": event_type, "uri": uri, "data": data }]));
+
+    self
+  }
+
+  /// Binds to a random port and serves in the background
+  pub fn start(self) -> MockLcuHandle {
+    let identity = Pkcs12::from_der(IDENTITY, IDENTITY_PASSWORD).unwrap();
+    let acceptor = Arc::new(TlsAcceptor::builder(identity).unwrap().build().unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let mock = Arc::new(self);
+    let log = requests.clone();
+
+    thread::spawn(move || {
+      for stream in listener.incoming().filter_map(|s| s.ok()) {
+        let acceptor = acceptor.clone();
+        let mock = mock.clone();
+        let log = log.clone();
+
+        thread::spawn(move || {
+          if let Ok(stream) = acceptor.accept(stream) {
+            mock.serve(stream, &log);
+          }
+        });
+      }
+    });
+
+    MockLcuHandle {
+      port: port,
+      requests: requests
+    }
+  }
+
+  fn serve(&self, stream: TlsStream<TcpStream>, log: &Mutex<Vec<String>>) {
+    match stream.into_ws() {
+      Ok(upgrade) => {
+        log.lock().unwrap().push("WEBSOCKET".to_owned());
+
+        if let Ok(client) = upgrade.use_protocol("wamp").accept() {
+          self.publish(client);
+        }
+      }
+      Err((mut stream, Some(request), _, _)) => {
+        let method = request.subject.0.to_string();
+        let uri = request.subject.1.to_string();
+        let path = uri.split('?').next().unwrap_or("").to_owned();
+
+        log.lock().unwrap().push(format!("{} {}", method, path));
+
+        let (status, body) = match self.routes.get(&(method, path)) {
+          Some(&(status, ref body)) => (status, body.clone()),
+          None => (404, json!({ "errorCode": "RESOURCE_NOT_FOUND", "httpStatus": 404, "message": "not found" })),
+        };
+
+        respond(&mut stream, status, &body);
+      }
+      Err(_) => {}
+    }
+  }
+
+  /// Waits for the WAMP subscription and plays back the scripted events
+  fn publish(&self, mut client: ::websocket::sync::Client<TlsStream<TcpStream>>) {
+    if client.recv_message().is_err() {
+      return;
+    }
+
+    for event in &self.events {
+      thread::sleep(self.event_delay);
+
+      if client.send_message(&OwnedMessage::Text(event.to_string())).is_err() {
+        return;
+      }
+    }
+
+    thread::sleep(self.event_delay);
+    client.send_message(&OwnedMessage::Close(None)).ok();
+  }
+}
+
+impl MockLcuHandle {
+  pub fn credentials(&self) -> Credentials {
+    Credentials {
+      pid: 0,
+      port: self.port.to_string(),
+      token: TOKEN.to_owned()
+    }
+  }
+
+  /// Every request served so far, as `METHOD /path` or `WEBSOCKET`
+  pub fn requests(&self) -> Vec<String> {
+    self.requests.lock().unwrap().clone()
+  }
+}
+
+fn respond<S: Write>(stream: &mut S, status: u16, body: &Value) {
+  let body = body.to_string();
+
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    if status < 400 { "OK" } else { "Error" },
+    body.len(),
+    body
+  ).ok();
+  stream.flush().ok();
+}
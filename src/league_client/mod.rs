@@ -1,119 +1,254 @@
 use std::time::Duration;
-use std::path::PathBuf;
-use std::process::Command;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use websocket::futures::sync::mpsc::unbounded;
+use websocket::futures::sync::mpsc::{unbounded, UnboundedSender};
 
 use errors::*;
+use trust::{Trust, TrustConfig};
+use updater::UpdateChecker;
 
 mod structs;
 mod util;
 mod websocket;
 mod client;
-
-use systray::{Application, SystrayEvent};
+mod file_ops;
+mod settings;
+mod config;
+mod lcu;
+mod retry;
+mod tracker;
+mod live_client;
+mod recording;
+mod locations;
+mod status;
+mod control;
+mod instance;
+mod editor;
+mod notify;
+mod reporter;
+mod session_end;
+mod watcher;
+mod web_editor;
+#[cfg(feature = "tray")]
+mod tray;
+#[cfg(any(feature = "tray", test))]
+mod tray_menu;
+#[cfg(test)]
+mod mock;
+
+use ctrlc;
 
 use self::client::*;
-use self::util::find_client;
+use self::notify::Notification;
+use self::reporter::Reporter;
+use self::status::SharedStatus;
+use self::util::{ensure_dir, find_client};
 pub use self::structs::*;
-
-static ICON: &'static [u8] = include_bytes!("../../resources/dark-binding.ico");
+pub use self::recording::replay;
+pub use self::locations::{DirectoryOverrides, Locations};
+pub use self::settings::Settings;
+pub use self::config::{validate_groups, ConfigFolder, ExportedBindings};
+pub use self::control::{send_request, Request, Response};
+pub use self::file_ops::diff_settings;
+pub use self::util::read_settings_json;
+
+/// Downloads a newer release in the background, offered from the tray once verified
+fn check_for_update(config: &ConfigFolder, status: SharedStatus, reporter: Reporter) {
+  let update_url = config.settings().unwrap_or_default().update_url;
+  let download_dir = config.updates_dir();
+
+  thread::spawn(move || {
+    let result = UpdateChecker::from_build(update_url.as_ref().map(|u| &u[..]), &download_dir)
+      .and_then(|checker| match checker {
+        Some(checker) => checker.check(crate_version!()),
+        None => {
+          debug!("no update key or manifest URL, not checking for updates");
+          Ok(None)
+        }
+      });
+
+    match result {
+      Ok(Some(update)) => {
+        status.set_update_available(Some(update.version.clone()));
+        reporter.notify(Notification::UpdateAvailable(update.version));
+      }
+      Ok(None) => {}
+      Err(e) => reporter.error("checking for updates", &e)
+    }
+  });
+}
 
 static DEFAULT_GROUPS_TOML: &'static [u8] = include_bytes!("./example_groups.toml");
 
-pub fn run() {
-  loop {
-    if let Some((c, dir)) = find_client().ok() {
-      let mut config_dir: PathBuf = [dir, "Config".to_owned()].iter().collect();
-      let mut client = LeagueClient::new(c.clone(), config_dir.clone());
+/// Options for a running instance
+#[derive(Debug, Default)]
+pub struct Options {
+  /// Records every websocket frame received to this file
+  pub record: Option<PathBuf>,
+  /// Only print the filesystem operations of config swaps
+  pub dry_run: bool,
+  /// How the League client's certificate is trusted
+  pub trust: TrustConfig,
+  /// Run without the system tray, controlled through signals and the control socket
+  pub headless: bool,
+  /// Look for a newer release once connected
+  pub check_updates: bool,
+  /// Where the Config folder is, when not next to the running client
+  pub directories: DirectoryOverrides
+}
 
-      let (client_sender, rx) = unbounded();
+pub fn run(options: &Options) -> Result<()> {
+  let locations = Locations::load()?;
 
-      let mut tray = Application::new().unwrap();
+  // Locking before waiting on the League client, when the folder is known
+  // without it, so a second launch hands off right away
+  let mut lock = None;
 
-      tray.set_icon_from_buffer(ICON, 64, 64).unwrap();
+  if let Ok(dir) = options.directories.resolve(&locations, None) {
+    if dir.is_dir() {
+      let data_dir = dir.join(".dark-binding");
+      ensure_dir(&data_dir)?;
 
-      config_dir.push(".dark-binding");
-      config_dir.push("groups.toml");
-
-      let is_editing = Arc::new(AtomicBool::new(false));
-      let sender_0 = client_sender.clone();
-
-      tray
-        .add_menu_item(&"Edit Champion Groups".to_owned(), move |_| {
-          if !is_editing.compare_and_swap(false, true, Ordering::SeqCst) {
-            // feelsbadman
-            let is_editing = is_editing.clone();
-            let groups_toml = config_dir.clone();
-            let sender_0 = sender_0.clone();
-
-            thread::spawn(move || {
-              let child = Command::new("notepad.exe")
-                .arg(&groups_toml.to_str().unwrap())
-                .spawn();
-
-              if let Err(_) = child {
-                is_editing.store(false, Ordering::SeqCst);
-                
-                return;
-              };
-
-              let result = child.unwrap().wait();
-
-              if result.is_ok() && result.unwrap().success() {
-                &sender_0
-                  .unbounded_send(LeagueClientFn::ReloadGroups)
-                  .expect("Couldn't reload groups");
-              }
-              
-              is_editing.store(false, Ordering::SeqCst);
-            });
-          }
-        })
-        .ok();
+      match instance::lock(&data_dir)? {
+        Some(held) => lock = Some(held),
+        None => return instance::hand_off(&data_dir)
+      }
+    }
+  }
 
-      let sender_1 = client_sender.clone();
+  // Signals reach whichever session is running, and quit right away between
+  // sessions. The handler only asks, the loop below quits once the session
+  // has put the base config back
+  let session_sender: Arc<Mutex<Option<UnboundedSender<LeagueClientFn>>>> = Arc::new(Mutex::new(None));
+  let signal_sender = session_sender.clone();
+  ctrlc::set_handler(move || match *signal_sender.lock().unwrap() {
+    Some(ref sender) => {
+      sender.unbounded_send(LeagueClientFn::Shutdown).ok();
+    }
+    None => process::exit(0)
+  }).ok();
+
+  // Windows ends the process when the end of the session has been answered,
+  // which waits for the base config to be restored
+  let (finished_sender, finished) = mpsc::channel();
+  let end_sender = session_sender.clone();
+  session_end::on_session_end(move || {
+    let requested = match *end_sender.lock().unwrap() {
+      Some(ref sender) => sender.unbounded_send(LeagueClientFn::Shutdown).is_ok(),
+      None => false
+    };
+
+    if requested {
+      finished.recv_timeout(Duration::from_secs(10)).ok();
+    }
+  });
+
+  loop {
+    if let Some((c, dir)) = find_client().ok() {
+      let mut config_dir = options.directories.resolve(&locations, Some(Path::new(&dir)))?;
+      let settings = Settings::read(&config_dir.join(".dark-binding")).unwrap_or_default();
+      let trust = Trust::load(&options.trust.or(&settings.certificate))?;
+
+      // A client still starting up or restarting can fail the handshake, look again later
+      if let Err(e) = trust.verify(&c.port) {
+        error!(
+          "op=\"verifying the League client certificate\" category={} outcome=error error={:?}",
+          e.category(),
+          e.to_string()
+        );
+
+        thread::sleep(Duration::from_secs(settings.poll_interval));
+        continue;
+      }
 
-      tray
-        .add_menu_item(&"Backup Config".to_string(), move |_| {
-          sender_1
-            .unbounded_send(LeagueClientFn::BackupConfig)
-            .expect("Couldn't backup config");
-        })
-        .ok();
+      debug!("using config folder {:?}", config_dir);
+      let mut client = LeagueClient::new(c.clone(), config_dir.clone()).with_trust(&trust)?;
 
-      let sender_2 = client_sender.clone();
+      client.set_dry_run(options.dry_run);
 
-      tray
-        .add_menu_item(&"Restore Config".to_string(), move |_| {
-          sender_2
-            .unbounded_send(LeagueClientFn::RestoreConfig)
-            .expect("Couldn't restore config");
-        })
-        .ok();
+      if let Some(ref path) = options.record {
+        client.record_to(path)?;
+      }
 
-      tray.add_menu_separator().ok();
+      let (client_sender, rx) = unbounded();
+
+      config_dir.push(".dark-binding");
+      ensure_dir(&config_dir)?;
+
+      // The client's folder can differ from the one known without it
+      if !lock.as_ref().map_or(false, |held: &instance::InstanceLock| held.is_in(&config_dir)) {
+        lock = match instance::lock(&config_dir)? {
+          Some(held) => Some(held),
+          None => return instance::hand_off(&config_dir)
+        };
+      }
+
+      ::logging::log_to(&client.config().logs_dir())?;
+
+      let _control = control::listen(&config_dir, client_sender.clone(), client.status())?;
+      let _watcher = watcher::watch(client.config().clone(), client_sender.clone(), Duration::from_secs(1));
+      let web_editor = web_editor::WebEditor::serve(client.config().clone(), client_sender.clone())?;
+
+      println!("champion groups editor: {}", web_editor.url());
+
+      if options.check_updates {
+        check_for_update(client.config(), client.status(), client.reporter().clone());
+      }
+
+      *session_sender.lock().unwrap() = Some(client_sender.clone());
+
+      #[cfg(feature = "tray")]
+      let tray = if options.headless {
+        None
+      } else {
+        match tray::Tray::show(client.config().clone(), web_editor.url(), client.status(), client_sender.clone()) {
+          Ok(tray) => Some(tray),
+          Err(e) => {
+            warn!("op=show_tray outcome=headless error={:?}", e.to_string());
+            None
+          }
+        }
+      };
 
-      let sender_3 = client_sender.clone();
+      let result = client.init(rx);
 
-      tray
-        .add_menu_item(&"Quit".to_string(), move |sender| {
-          sender_3.unbounded_send(LeagueClientFn::Shutdown).unwrap();
-          sender.send(SystrayEvent::Quit).ok();
-        })
-        .ok();
+      #[cfg(feature = "tray")]
+      {
+        if let Some(tray) = tray {
+          tray.quit();
+        }
+      }
 
-      let tray_sender = tray.wait_for_message();
+      *session_sender.lock().unwrap() = None;
 
-      let _result = client.init(rx);
+      match result {
+        Err(Error(ErrorKind::ForceShutdown, _)) => {
+          finished_sender.send(()).ok();
 
-      tray_sender.send(SystrayEvent::Quit).ok();
+          break;
+        }
+        Ok(_) => {}
+        Err(e) => client.reporter().error("watching the League client", &e)
+      }
 
-      break;
+      // The League client was closed, put the base config back and wait for the next one
+      info!("op=watch_client outcome=closed");
+      client.shutdown().ok();
     }
 
-    thread::sleep(Duration::from_secs(60));
+    // Settings of the folder used when no client is running, if it's known
+    let poll_interval = options
+      .directories
+      .resolve(&locations, None)
+      .ok()
+      .and_then(|dir| Settings::read(&dir.join(".dark-binding")).ok())
+      .unwrap_or_default()
+      .poll_interval;
+
+    thread::sleep(Duration::from_secs(poll_interval));
   }
+
+  Ok(())
 }
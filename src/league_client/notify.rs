@@ -0,0 +1,169 @@
+//! Feedback about config swaps for people not watching a terminal
+//!
+//! Every notification is printed, and also shown as a desktop notification when
+//! its kind is enabled in the settings. Desktop notifications go through whatever
+//! the OS ships: `notify-send`, `osascript` or a PowerShell balloon tip.
+
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::thread::{self, JoinHandle};
+
+use league_client::settings::NotificationSettings;
+
+static TITLE: &'static str = "Dark Binding";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+  /// A group's bindings were linked
+  GroupApplied(String),
+  /// The picked champion isn't in any group, so the base bindings are used
+  NotInGroup(String),
+  /// The base bindings were put back on request
+  Restored,
+  /// Something failed, with what was being done and the error
+  Failed(String, String),
+  /// A newer release is ready to be installed
+  UpdateAvailable(String),
+  /// A newer release replaced the executable
+  UpdateInstalled(String),
+  /// Launching again found this instance running
+  AlreadyRunning,
+  /// Asked for from the tray
+  Status(String)
+}
+
+impl Notification {
+  pub fn message(&self) -> String {
+    match *self {
+      Notification::GroupApplied(ref group) => format!("Bindings for group {} applied", group),
+      Notification::NotInGroup(ref champion) => format!("{} isn't in any group, using your base bindings", champion),
+      Notification::Restored => "Base bindings restored".to_owned(),
+      Notification::Failed(ref operation, ref error) => format!("Failed {}: {}", operation, error),
+      Notification::UpdateAvailable(ref version) => format!("Version {} is ready to install from the tray", version),
+      Notification::UpdateInstalled(ref version) => format!("Version {} installed, it starts with the next launch", version),
+      Notification::AlreadyRunning => "Dark Binding is already running".to_owned(),
+      Notification::Status(ref status) => status.to_owned()
+    }
+  }
+
+  fn enabled(&self, settings: &NotificationSettings) -> bool {
+    match *self {
+      Notification::GroupApplied(_) => settings.group_applied,
+      Notification::NotInGroup(_) => settings.not_in_group,
+      Notification::Restored => settings.restored,
+      Notification::Failed(..) => settings.errors,
+      Notification::UpdateAvailable(_) | Notification::UpdateInstalled(_) => settings.update_available,
+      // Answer the launch or the tray, so they're shown even with every other kind off
+      Notification::AlreadyRunning | Notification::Status(_) => true
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+  settings: NotificationSettings
+}
+
+impl Notifier {
+  pub fn set_settings(&mut self, settings: NotificationSettings) {
+    self.settings = settings;
+  }
+
+  /// Prints `notification`, showing it on the desktop too if enabled
+  pub fn notify(&self, notification: Notification) {
+    let message = notification.message();
+
+    println!("{}", message);
+
+    if !notification.enabled(&self.settings) {
+      return;
+    }
+
+    // Not waiting, a slow notification daemon mustn't hold up the swap
+    if let Err(e) = spawn_reaped(desktop_command(&message)) {
+      debug!("unable to show notification: {}", e);
+    }
+  }
+}
+
+/// Spawns `command`, waiting for it on a thread of its own so it doesn't stay
+/// behind as a zombie
+fn spawn_reaped(mut command: Command) -> io::Result<JoinHandle<io::Result<ExitStatus>>> {
+  let mut child = command.spawn()?;
+
+  Ok(thread::spawn(move || child.wait()))
+}
+
+#[cfg(windows)]
+fn desktop_command(message: &str) -> Command {
+  let script = format!(
+    "Add-Type -AssemblyName System.Windows.Forms; \
+     $n = New-Object System.Windows.Forms.NotifyIcon; \
+     $n.Icon = [System.Drawing.SystemIcons]::Information; \
+     $n.Visible = $true; \
+     $n.ShowBalloonTip(5000, '{}', '{}', 'Info'); \
+     Start-Sleep -Seconds 6; \
+     $n.Dispose()",
+    TITLE,
+    message.replace('\'', "''")
+  );
+
+  let mut command = Command::new("powershell");
+  command.args(&["-NoProfile", "-WindowStyle", "Hidden", "-Command", &script]);
+
+  command
+}
+
+#[cfg(target_os = "macos")]
+fn desktop_command(message: &str) -> Command {
+  let script = format!(
+    "display notification {} with title {}",
+    applescript_string(message),
+    applescript_string(TITLE)
+  );
+
+  let mut command = Command::new("osascript");
+  command.args(&["-e", &script]);
+
+  command
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn desktop_command(message: &str) -> Command {
+  let mut command = Command::new("notify-send");
+  command.args(&["--app-name", TITLE, TITLE, message]);
+
+  command
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_string(s: &str) -> String {
+  format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  /// Each kind of notification has its own toggle
+  fn follows_toggles() {
+    let settings = NotificationSettings {
+      restored: false,
+      ..NotificationSettings::default()
+    };
+
+    assert!(Notification::GroupApplied("adc".to_owned()).enabled(&settings));
+    assert!(Notification::Failed("swapping bindings".to_owned(), "oops".to_owned()).enabled(&settings));
+    assert!(!Notification::Restored.enabled(&settings));
+  }
+
+  #[cfg(unix)]
+  #[test]
+  /// Spawned notifications are waited for
+  fn reaps_notifications() {
+    let waited = spawn_reaped(Command::new("true")).unwrap();
+
+    assert!(waited.join().unwrap().unwrap().success());
+  }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rand::{thread_rng, Rng};
+use serde_json;
+use websocket::OwnedMessage;
+
+use errors::*;
+
+use league_client::client::{Credentials, LeagueClient};
+use league_client::structs::LocalSummoner;
+use league_client::util::copy_dir;
+use league_client::websocket::LeagueSocketHandler;
+
+/// One line of a recording
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[derive(Debug, Serialize, Deserialize)]
+pub enum RecordedLine {
+  /// Client state the frames were received with, always the first line
+  Session {
+    summoner: Option<LocalSummoner>,
+    champions: HashMap<String, i32>
+  },
+  /// Raw WAMP frame, `ts` is in milliseconds since the unix epoch
+  Frame { ts: u64, frame: String }
+}
+
+/// Writes every raw websocket frame received to a JSONL file
+pub struct Recorder {
+  file: File
+}
+
+impl Recorder {
+  pub fn create(path: &Path) -> Result<Recorder> {
+    let file = OpenOptions::new()
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(path)
+      .chain_err(|| format!("unable to create recording {:?}", path))?;
+
+    Ok(Recorder { file: file })
+  }
+
+  pub fn session(&mut self, summoner: Option<LocalSummoner>, champions: HashMap<String, i32>) -> Result<()> {
+    self.write(&RecordedLine::Session {
+      summoner: summoner,
+      champions: champions
+    })
+  }
+
+  pub fn frame(&mut self, msg: &OwnedMessage) -> Result<()> {
+    let frame = match *msg {
+      OwnedMessage::Text(ref text) => text.clone(),
+      OwnedMessage::Binary(ref bytes) => String::from_utf8_lossy(bytes).into_owned(),
+      _ => return Ok(())
+    };
+
+    self.write(&RecordedLine::Frame {
+      ts: unix_millis(),
+      frame: frame
+    })
+  }
+
+  fn write(&mut self, line: &RecordedLine) -> Result<()> {
+    serde_json::to_writer(&mut self.file, line)?;
+    self.file.write_all(b"\n")?;
+
+    Ok(self.file.flush()?)
+  }
+}
+
+fn unix_millis() -> u64 {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or(Duration::from_secs(0));
+
+  now.as_secs() * 1000 + u64::from(now.subsec_nanos() / 1_000_000)
+}
+
+fn read_recording(path: &Path) -> Result<Vec<RecordedLine>> {
+  let file = File::open(path).chain_err(|| format!("unable to open recording {:?}", path))?;
+  let mut lines = Vec::new();
+
+  for line in BufReader::new(file).lines() {
+    let line = line?;
+
+    if !line.trim().is_empty() {
+      lines.push(serde_json::from_str(&line)?);
+    }
+  }
+
+  Ok(lines)
+}
+
+/// Feeds a recording through `on_message` against a copy of `config_dir`
+///
+/// Frames are spaced like they were recorded, divided by `speed`, and the swap
+/// debounce is divided the same way so lock-ins settle like they did live. At
+/// speed 0 frames follow each other without delays and the debounce is kept,
+/// so only the champion locked in when champ select ends is applied. The live
+/// client API of a game running now isn't asked. Returns the temporary config
+/// directory so the result can be inspected.
+pub fn replay(recording: &Path, config_dir: &Path, speed: f64, dry_run: bool) -> Result<PathBuf> {
+  let lines = read_recording(recording)?;
+
+  let temp_dir = ::std::env::temp_dir().join(format!("dark-binding-replay-{}", thread_rng().gen::<u32>()));
+  fs::create_dir_all(&temp_dir)?;
+
+  for name in &["PersistedSettings.json", "PersistedSettings.bak"] {
+    if config_dir.join(name).exists() {
+      fs::copy(config_dir.join(name), temp_dir.join(name))?;
+    }
+  }
+
+  if config_dir.join(".dark-binding").exists() {
+    copy_dir(&config_dir.join(".dark-binding"), &temp_dir.join(".dark-binding"))?;
+  }
+
+  debug!("replaying {:?} against {:?}", recording, temp_dir);
+
+  let credentials = Credentials {
+    pid: 0,
+    port: "0".to_owned(),
+    token: String::new()
+  };
+
+  let mut client = LeagueClient::new(credentials, temp_dir.clone());
+  client.set_dry_run(dry_run);
+  client.set_live_client(false);
+  client.reload_settings()?;
+
+  if speed > 0.0 {
+    let debounce = client.champion_tracker.debounce();
+    let debounce_ms = debounce.as_secs() * 1000 + u64::from(debounce.subsec_nanos() / 1_000_000);
+
+    client
+      .champion_tracker
+      .set_debounce(Duration::from_millis((debounce_ms as f64 / speed) as u64));
+  }
+
+  let mut last_ts = None;
+
+  for line in lines {
+    match line {
+      RecordedLine::Session { summoner, champions } => {
+        client.restore_session(summoner, champions)?;
+      }
+      RecordedLine::Frame { ts, frame } => {
+        if let Some(last) = last_ts {
+          if speed > 0.0 && ts > last {
+            thread::sleep(Duration::from_millis(((ts - last) as f64 / speed) as u64));
+          }
+        }
+
+        last_ts = Some(ts);
+        client.on_message(OwnedMessage::Text(frame));
+      }
+    }
+  }
+
+  Ok(temp_dir)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::{self, File};
+  use std::io::Write;
+  use tempdir::TempDir;
+
+  use super::*;
+
+  /// Config folder with a base config and two groups, and a recording of `frames`
+  fn recorded_session(frames: Vec<(u64, String)>) -> (TempDir, PathBuf) {
+    let config = TempDir::new("dark-binding").unwrap();
+    let recording = config.path().join("session.jsonl");
+
+    File::create(config.path().join("PersistedSettings.json"))
+      .unwrap()
+      .write_all(br#"{ "description": "base", "files": [] }"#)
+      .unwrap();
+    fs::create_dir_all(config.path().join(".dark-binding")).unwrap();
+    File::create(config.path().join(".dark-binding").join("groups.toml"))
+      .unwrap()
+      .write_all(b"[groups]\nmages = [\"Annie\"]\nadc = [\"Jinx\"]\n")
+      .unwrap();
+
+    let mut file = File::create(&recording).unwrap();
+    let session = json!({ "kind": "session", "summoner": {
+      "displayName": "tester", "accountId": "1", "summonerId": "1",
+      "summonerLevel": 30, "profileIconId": 1
+    }, "champions": { "ANNIE": 1, "JINX": 222 } });
+
+    writeln!(file, "{}", session).unwrap();
+
+    for (ts, frame) in frames {
+      writeln!(file, "{}", json!({ "kind": "frame", "ts": ts, "frame": frame })).unwrap();
+    }
+
+    (config, recording)
+  }
+
+  fn champ_select(champion_id: i32) -> String {
+    json!([8, "OnJsonApiEvent", {
+      "eventType": "Update",
+      "uri": "/lol-champ-select/v1/session",
+      "data": {
+        "localPlayerCellId": 0,
+        "myTeam": [{ "cellId": 0, "championId": champion_id, "summonerId": 1 }],
+        "timer": { "phase": "FINALIZATION" }
+      }
+    }]).to_string()
+  }
+
+  fn champ_select_end() -> String {
+    json!([8, "OnJsonApiEvent", {
+      "eventType": "Delete", "uri": "/lol-champ-select/v1/session", "data": null
+    }]).to_string()
+  }
+
+  #[test]
+  /// A recorded ARAM bench swap ends up linking the final champion's group
+  fn replays_bench_swap() {
+    let (config, recording) = recorded_session(vec![
+      (1000, champ_select(1)),
+      (1200, champ_select(222)),
+      (1400, champ_select_end())
+    ]);
+
+    let replayed = replay(&recording, config.path(), 100.0, false).unwrap();
+
+    assert_eq!(
+      replayed.join("PersistedSettings.json").read_link().unwrap(),
+      replayed.join(".dark-binding").join("adc.json")
+    );
+    assert!(!is_symlink_in(config.path()));
+
+    fs::remove_dir_all(replayed).ok();
+  }
+
+  #[test]
+  /// The debounce shrinks with the replay speed, a lock-in stable for longer
+  /// than the recorded debounce is applied before champ select ends
+  fn scales_debounce_with_speed() {
+    let (config, recording) = recorded_session(vec![
+      (1000, champ_select(222)),
+      (4000, champ_select(222))
+    ]);
+
+    let replayed = replay(&recording, config.path(), 100.0, false).unwrap();
+
+    assert_eq!(
+      replayed.join("PersistedSettings.json").read_link().unwrap(),
+      replayed.join(".dark-binding").join("adc.json")
+    );
+
+    fs::remove_dir_all(replayed).ok();
+  }
+
+  fn is_symlink_in(dir: &::std::path::Path) -> bool {
+    fs::symlink_metadata(dir.join("PersistedSettings.json"))
+      .unwrap()
+      .file_type()
+      .is_symlink()
+  }
+}
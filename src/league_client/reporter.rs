@@ -0,0 +1,97 @@
+use errors::*;
+
+use league_client::notify::{Notification, Notifier};
+use league_client::settings::NotificationSettings;
+use league_client::status::{LastError, SharedStatus};
+
+/// Where every outcome of the client's work ends up
+///
+/// Errors are logged with their category, kept as the status' last error and
+/// notified, so none of them disappear in the event loop.
+#[derive(Clone)]
+pub struct Reporter {
+  notifier: Notifier,
+  status: SharedStatus
+}
+
+impl Reporter {
+  pub fn new(status: SharedStatus) -> Reporter {
+    Reporter {
+      notifier: Notifier::default(),
+      status: status
+    }
+  }
+
+  pub fn set_notifications(&mut self, settings: NotificationSettings) {
+    self.notifier.set_settings(settings);
+  }
+
+  pub fn notify(&self, notification: Notification) {
+    self.notifier.notify(notification);
+  }
+
+  /// Reports an error that happened while doing `operation`
+  pub fn error(&self, operation: &str, e: &Error) {
+    let causes: Vec<String> = e.iter().map(|e| e.to_string()).collect();
+
+    error!(
+      "op={:?} category={} outcome=error error={:?}",
+      operation,
+      e.category(),
+      causes.join(": ")
+    );
+
+    self.status.set_last_error(LastError::new(operation, e));
+    self.notifier.notify(Notification::Failed(operation.to_owned(), e.to_string()));
+  }
+
+  /// Notifies a successful outcome, reports and passes on a failed one
+  pub fn outcome(&self, operation: &str, result: Result<Notification>) -> Result<()> {
+    match result {
+      Ok(notification) => {
+        self.notify(notification);
+
+        Ok(())
+      }
+      Err(e) => {
+        self.error(operation, &e);
+
+        Err(e)
+      }
+    }
+  }
+
+  /// Reports the error of `result`, if any
+  pub fn check<T>(&self, operation: &str, result: Result<T>) {
+    if let Err(e) = result {
+      self.error(operation, &e);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use super::*;
+
+  #[test]
+  /// Errors are kept with their category as the last error
+  fn records_last_error() {
+    let status = SharedStatus::new(PathBuf::from("/nonexistent"));
+    let mut reporter = Reporter::new(status.clone());
+
+    reporter.set_notifications(NotificationSettings {
+      errors: false,
+      ..NotificationSettings::default()
+    });
+
+    let error: Error = ErrorKind::LinkPermission(PathBuf::from("PersistedSettings.json")).into();
+    reporter.check::<()>("swapping bindings", Err(error));
+
+    let last_error = status.snapshot().last_error.unwrap();
+
+    assert_eq!(last_error.operation, "swapping bindings");
+    assert_eq!(last_error.category, ErrorCategory::LinkPermission);
+  }
+}
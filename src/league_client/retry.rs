@@ -0,0 +1,252 @@
+use std::cmp;
+use std::error::Error as StdError;
+use std::io::{self, ErrorKind as IoErrorKind};
+use std::thread;
+use std::time::Duration;
+use rand::{thread_rng, Rng};
+
+use errors::*;
+
+/// How LCU requests are retried
+///
+/// The LCU routinely answers 404/503 for a few seconds after startup, so
+/// failed attempts are retried with exponential backoff plus random jitter.
+/// Requests that may have reached the LCU are only retried when they are
+/// idempotent, a failed POST could otherwise be applied twice. Only a refused
+/// connection is sure not to have reached it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+  /// Total number of attempts, including the first one
+  pub attempts: u32,
+  pub base_delay_ms: u64,
+  pub max_delay_ms: u64,
+  pub jitter_ms: u64,
+  /// HTTP statuses worth another attempt to idempotent requests
+  pub retryable_statuses: Vec<u16>
+}
+
+impl Default for RetryPolicy {
+  fn default() -> RetryPolicy {
+    RetryPolicy {
+      attempts: 3,
+      base_delay_ms: 500,
+      max_delay_ms: 4000,
+      jitter_ms: 500,
+      retryable_statuses: vec![429, 500, 502, 503, 504]
+    }
+  }
+}
+
+impl RetryPolicy {
+  /// Single attempt, fail immediately
+  pub fn none() -> RetryPolicy {
+    RetryPolicy {
+      attempts: 1,
+      ..RetryPolicy::default()
+    }
+  }
+
+  /// Patient policy for the calls made while the client is still booting,
+  /// when a 404 only means the endpoint isn't up yet
+  pub fn startup() -> RetryPolicy {
+    RetryPolicy {
+      attempts: 10,
+      base_delay_ms: 1000,
+      max_delay_ms: 8000,
+      retryable_statuses: vec![404, 429, 500, 502, 503, 504],
+      ..RetryPolicy::default()
+    }
+  }
+
+  /// Delay before the attempt following `attempt` (0 based)
+  pub fn delay(&self, attempt: u32) -> Duration {
+    let backoff = cmp::min(self.base_delay_ms.saturating_mul(1u64 << cmp::min(attempt, 16)), self.max_delay_ms);
+    let jitter = match self.jitter_ms {
+      0 => 0,
+      j => thread_rng().gen_range::<u64>(0, j)
+    };
+
+    Duration::from_millis(backoff + jitter)
+  }
+
+  /// Whether `error` is worth another attempt, `idempotent` requests being
+  /// the only ones retried once the LCU may have seen them
+  pub fn is_retryable(&self, error: &Error, idempotent: bool) -> bool {
+    match *error.kind() {
+      ErrorKind::LcuApi(status, _, _) => idempotent && self.retryable_statuses.contains(&status),
+      ErrorKind::Request(ref e) => match e.status() {
+        Some(status) => idempotent && self.retryable_statuses.contains(&status.as_u16()),
+        None => is_connect_error(e, idempotent)
+      },
+      ErrorKind::Io(ref e) => is_connect_io_error(e, idempotent),
+      _ => false
+    }
+  }
+
+  /// Runs `f` until it succeeds, fails with a non retryable error or runs out
+  /// of attempts
+  pub fn run<T, F>(&self, idempotent: bool, mut f: F) -> Result<T>
+    where F: FnMut() -> Result<T> {
+    let mut attempt = 0;
+
+    loop {
+      match f() {
+        Ok(res) => return Ok(res),
+        Err(e) => {
+          attempt += 1;
+
+          if attempt >= self.attempts || !self.is_retryable(&e, idempotent) {
+            return Err(e);
+          }
+
+          let delay = self.delay(attempt - 1);
+          debug!("attempt {} failed ({}), retrying in {:?}", attempt, e, delay);
+
+          thread::sleep(delay);
+        }
+      }
+    }
+  }
+}
+
+/// Whether a request failed on its connection, TLS and protocol errors won't
+/// go away by trying again
+fn is_connect_error(error: &::reqwest::Error, idempotent: bool) -> bool {
+  let mut cause: Option<&(StdError + 'static)> = error.get_ref().map(|e| e as &(StdError + 'static));
+
+  while let Some(e) = cause {
+    if let Some(io) = e.downcast_ref::<io::Error>() {
+      return is_connect_io_error(io, idempotent);
+    }
+
+    cause = e.source();
+  }
+
+  false
+}
+
+/// A connection that was never made can always be tried again, one that broke
+/// or timed out may have delivered the request already
+fn is_connect_io_error(error: &io::Error, idempotent: bool) -> bool {
+  match error.kind() {
+    IoErrorKind::ConnectionRefused | IoErrorKind::NotConnected => true,
+    IoErrorKind::ConnectionReset | IoErrorKind::ConnectionAborted | IoErrorKind::TimedOut => idempotent,
+    _ => false
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::Cell;
+
+  use super::*;
+
+  fn quick(attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+      attempts: attempts,
+      base_delay_ms: 0,
+      max_delay_ms: 0,
+      jitter_ms: 0,
+      ..RetryPolicy::default()
+    }
+  }
+
+  fn unavailable() -> Error {
+    ErrorKind::LcuApi(503, String::new(), String::new()).into()
+  }
+
+  #[test]
+  /// Backoff doubles up to the maximum, plus less than `jitter_ms`
+  fn delay_backs_off() {
+    let policy = RetryPolicy::default();
+
+    assert!(policy.delay(0) >= Duration::from_millis(500));
+    assert!(policy.delay(0) < Duration::from_millis(1000));
+    assert!(policy.delay(1) >= Duration::from_millis(1000));
+    assert!(policy.delay(10) >= Duration::from_millis(4000));
+    assert!(policy.delay(10) < Duration::from_millis(4500));
+    assert_eq!(RetryPolicy { jitter_ms: 0, ..policy }.delay(40), Duration::from_millis(4000));
+  }
+
+  #[test]
+  /// Statuses are only retried for idempotent requests, refused connections for every one
+  fn retries_idempotent_statuses_and_connect_errors() {
+    let policy = RetryPolicy::default();
+    let refused: Error = io::Error::new(IoErrorKind::ConnectionRefused, "refused").into();
+    let denied: Error = io::Error::new(IoErrorKind::PermissionDenied, "denied").into();
+
+    assert!(policy.is_retryable(&unavailable(), true));
+    assert!(!policy.is_retryable(&unavailable(), false));
+    assert!(!policy.is_retryable(&ErrorKind::LcuApi(400, String::new(), String::new()).into(), true));
+    assert!(policy.is_retryable(&refused, false));
+    assert!(!policy.is_retryable(&denied, true));
+  }
+
+  #[test]
+  /// A missing endpoint is only waited for at startup
+  fn retries_not_found_at_startup_only() {
+    let not_found: Error = ErrorKind::LcuApi(404, String::new(), String::new()).into();
+
+    assert!(!RetryPolicy::default().is_retryable(&not_found, true));
+    assert!(RetryPolicy::startup().is_retryable(&not_found, true));
+  }
+
+  #[test]
+  /// Retryable failures are attempted again until the attempts run out
+  fn runs_until_attempts_run_out() {
+    let calls = Cell::new(0);
+    let result: Result<()> = quick(3).run(true, || {
+      calls.set(calls.get() + 1);
+      Err(unavailable())
+    });
+
+    assert!(result.is_err());
+    assert_eq!(calls.get(), 3);
+
+    calls.set(0);
+    let result = quick(3).run(true, || {
+      calls.set(calls.get() + 1);
+      match calls.get() {
+        1 => Err(unavailable()),
+        n => Ok(n)
+      }
+    });
+
+    assert_eq!(result.unwrap(), 2);
+  }
+
+  #[test]
+  /// A POST answered with an error isn't sent again
+  fn does_not_repeat_non_idempotent_requests() {
+    let calls = Cell::new(0);
+    let result: Result<()> = quick(3).run(false, || {
+      calls.set(calls.get() + 1);
+      Err(unavailable())
+    });
+
+    assert!(result.is_err());
+    assert_eq!(calls.get(), 1);
+  }
+
+  #[test]
+  /// A POST whose connection was reset may have been applied, it isn't sent again
+  fn does_not_repeat_non_idempotent_requests_after_reset() {
+    let calls = Cell::new(0);
+    let result: Result<()> = quick(3).run(false, || {
+      calls.set(calls.get() + 1);
+      Err(io::Error::new(IoErrorKind::ConnectionReset, "reset").into())
+    });
+
+    assert!(result.is_err());
+    assert_eq!(calls.get(), 1);
+
+    calls.set(0);
+    let result: Result<()> = quick(3).run(true, || {
+      calls.set(calls.get() + 1);
+      Err(io::Error::new(IoErrorKind::ConnectionReset, "reset").into())
+    });
+
+    assert!(result.is_err());
+    assert_eq!(calls.get(), 3);
+  }
+}
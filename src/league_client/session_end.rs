@@ -0,0 +1,153 @@
+//! Holding off the end of the Windows session until the base config is back
+//!
+//! Logoff and shutdown don't send signals on Windows. A console process gets a
+//! console control event and is ended as soon as its handler returns, while a
+//! process that loaded user32, like the tray, only gets `WM_QUERYENDSESSION`
+//! and `WM_ENDSESSION` and is ended once they're answered. Both are answered
+//! only after the handler has run.
+//!
+//! Elsewhere the session ends with SIGTERM, which the ctrlc handler gets.
+
+#[cfg(windows)]
+use std::sync::Mutex;
+
+#[cfg(windows)]
+lazy_static! {
+  static ref HANDLER: Mutex<Option<Box<Fn() + Send>>> = Mutex::new(None);
+}
+
+/// Runs `handler` before the session ends, which waits until it returns
+///
+/// Has to be called after `ctrlc::set_handler`, Windows asks the last
+/// registered console handler first
+#[cfg(windows)]
+pub fn on_session_end<F>(handler: F)
+where
+  F: Fn() + Send + 'static
+{
+  use std::thread;
+  use winapi::shared::minwindef::TRUE;
+  use winapi::um::consoleapi::SetConsoleCtrlHandler;
+
+  *HANDLER.lock().unwrap() = Some(Box::new(handler));
+
+  if unsafe { SetConsoleCtrlHandler(Some(windows::console_handler), TRUE) } == 0 {
+    warn!("unable to watch for console logoff and shutdown events");
+  }
+
+  thread::spawn(|| unsafe { windows::watch_session() });
+}
+
+#[cfg(not(windows))]
+pub fn on_session_end<F>(_handler: F)
+where
+  F: Fn() + Send + 'static
+{
+}
+
+/// Runs the handler, a second caller waits for the first one to be done
+#[cfg(windows)]
+fn run_handler() {
+  if let Some(ref handler) = *HANDLER.lock().unwrap() {
+    handler();
+  }
+}
+
+#[cfg(windows)]
+mod windows {
+  use std::ffi::OsStr;
+  use std::mem;
+  use std::os::windows::ffi::OsStrExt;
+  use std::ptr;
+  use winapi::shared::minwindef::{BOOL, DWORD, FALSE, LPARAM, LRESULT, TRUE, UINT, WPARAM};
+  use winapi::shared::windef::HWND;
+  use winapi::um::libloaderapi::GetModuleHandleW;
+  use winapi::um::wincon::{CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT};
+  use winapi::um::winuser::{CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+                            TranslateMessage, MSG, WM_ENDSESSION, WM_QUERYENDSESSION, WNDCLASSW};
+
+  use super::run_handler;
+
+  /// Ctrl-C and Ctrl-Break are left to the ctrlc handler
+  pub unsafe extern "system" fn console_handler(event: DWORD) -> BOOL {
+    match event {
+      CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
+        run_handler();
+
+        TRUE
+      }
+      _ => FALSE
+    }
+  }
+
+  unsafe extern "system" fn window_proc(window: HWND, message: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    match message {
+      WM_QUERYENDSESSION => TRUE as LRESULT,
+      // `w_param` is 0 when the session end was cancelled
+      WM_ENDSESSION => {
+        if w_param != 0 {
+          run_handler();
+        }
+
+        0
+      }
+      _ => DefWindowProcW(window, message, w_param, l_param)
+    }
+  }
+
+  fn wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(Some(0)).collect()
+  }
+
+  /// Pumps the messages of a hidden window until the process ends
+  pub unsafe fn watch_session() {
+    let class_name = wide("DarkBindingSessionEnd");
+    let instance = GetModuleHandleW(ptr::null());
+
+    let class = WNDCLASSW {
+      style: 0,
+      lpfnWndProc: Some(window_proc),
+      cbClsExtra: 0,
+      cbWndExtra: 0,
+      hInstance: instance,
+      hIcon: ptr::null_mut(),
+      hCursor: ptr::null_mut(),
+      hbrBackground: ptr::null_mut(),
+      lpszMenuName: ptr::null(),
+      lpszClassName: class_name.as_ptr()
+    };
+
+    if RegisterClassW(&class) == 0 {
+      warn!("unable to watch for logoff and shutdown");
+      return;
+    }
+
+    // Never shown, but top-level: message-only windows don't get the session broadcasts
+    let window = CreateWindowExW(
+      0,
+      class_name.as_ptr(),
+      class_name.as_ptr(),
+      0,
+      0,
+      0,
+      0,
+      0,
+      ptr::null_mut(),
+      ptr::null_mut(),
+      instance,
+      ptr::null_mut()
+    );
+
+    if window.is_null() {
+      warn!("unable to watch for logoff and shutdown");
+      return;
+    }
+
+    let mut message: MSG = mem::zeroed();
+
+    while GetMessageW(&mut message, ptr::null_mut(), 0, 0) > 0 {
+      TranslateMessage(&message);
+      DispatchMessageW(&message);
+    }
+  }
+}
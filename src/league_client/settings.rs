@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use toml;
+
+use errors::*;
+use trust::TrustConfig;
+
+use league_client::util::ensure_dir;
+
+static DEFAULT_SETTINGS_TOML: &'static [u8] = include_bytes!("./example_settings.toml");
+
+/// Application settings, `settings.toml` next to `groups.toml`
+#[serde(default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+  /// Command opening groups.toml from the tray, the file path is appended. Falls
+  /// back to `$VISUAL`, `$EDITOR` and then the OS default application
+  pub editor: Option<String>,
+  /// Seconds between looks for a running League client
+  pub poll_interval: u64,
+  /// Milliseconds a champion has to stay selected before its group is linked
+  pub swap_debounce_ms: u64,
+  /// Settings files synced back into the base config when a group is unlinked,
+  /// every other file stays specific to the group
+  pub shared_files: Vec<String>,
+  /// Release manifest checked for updates, instead of the one built in
+  pub update_url: Option<String>,
+  /// Every account uses the same groups and group configs instead of a profile
+  /// of its own
+  pub share_profiles: bool,
+  pub notifications: NotificationSettings,
+  pub retry: RetrySettings,
+  /// How the League client's certificate is trusted, the command line options
+  /// take precedence
+  pub certificate: TrustConfig,
+  pub logging: LoggingSettings
+}
+
+/// Which desktop notifications are shown, every one is still printed
+#[serde(default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+  /// A group's bindings were applied
+  pub group_applied: bool,
+  /// The picked champion isn't in any group
+  pub not_in_group: bool,
+  /// The base bindings were restored from the tray or the control API
+  pub restored: bool,
+  /// Swapping, backing up or restoring failed
+  pub errors: bool,
+  /// A newer release was downloaded and can be installed from the tray
+  pub update_available: bool
+}
+
+impl Default for NotificationSettings {
+  fn default() -> NotificationSettings {
+    NotificationSettings {
+      group_applied: true,
+      not_in_group: true,
+      restored: true,
+      errors: true,
+      update_available: true
+    }
+  }
+}
+
+/// How often failed LCU requests are attempted
+#[serde(default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetrySettings {
+  /// Attempts of a request, including the first one
+  pub attempts: u32,
+  /// Attempts of the requests made while the client is starting up
+  pub startup_attempts: u32
+}
+
+impl Default for RetrySettings {
+  fn default() -> RetrySettings {
+    RetrySettings {
+      attempts: 3,
+      startup_attempts: 10
+    }
+  }
+}
+
+#[serde(default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LoggingSettings {
+  /// Print debug output to the console, the log file always has it
+  pub verbose: bool
+}
+
+impl Default for Settings {
+  fn default() -> Settings {
+    Settings {
+      editor: None,
+      poll_interval: 60,
+      swap_debounce_ms: 1500,
+      shared_files: vec!["Game.cfg".to_owned()],
+      update_url: None,
+      share_profiles: false,
+      notifications: NotificationSettings::default(),
+      retry: RetrySettings::default(),
+      certificate: TrustConfig::default(),
+      logging: LoggingSettings::default()
+    }
+  }
+}
+
+impl Settings {
+  /// Reads `settings.toml` from the `.dark-binding` folder `dir`
+  pub fn read(dir: &Path) -> Result<Settings> {
+    let path = dir.join("settings.toml");
+    let mut s = String::new();
+
+    File::open(&path)?.read_to_string(&mut s)?;
+
+    Ok(toml::from_str(&s).chain_err(|| ErrorKind::Parse(format!("{:?}", path)))?)
+  }
+
+  /// Like `read`, writing the commented default settings first if there are none
+  pub fn load(dir: &Path) -> Result<Settings> {
+    if !dir.join("settings.toml").exists() {
+      ensure_dir(dir)?;
+      File::create(dir.join("settings.toml"))?.write_all(DEFAULT_SETTINGS_TOML)?;
+    }
+
+    Settings::read(dir)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::str;
+
+  use super::*;
+
+  #[test]
+  /// The commented settings written for new users are the defaults
+  fn example_matches_defaults() {
+    let example: Settings = toml::from_str(str::from_utf8(DEFAULT_SETTINGS_TOML).unwrap()).unwrap();
+
+    assert_eq!(example, Settings::default());
+  }
+
+  #[test]
+  /// Settings left out keep their default
+  fn fills_missing_settings() {
+    let settings: Settings = toml::from_str("poll_interval = 5\n[logging]\n").unwrap();
+
+    assert_eq!(settings.poll_interval, 5);
+    assert_eq!(settings.shared_files, Settings::default().shared_files);
+  }
+}
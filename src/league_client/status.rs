@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use errors::{Error, ErrorCategory};
+
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ConnectionState {
+  Connecting,
+  Connected,
+  Disconnected
+}
+
+/// Snapshot of a running instance, as reported by the control endpoint
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Status {
+  pub connection: ConnectionState,
+  pub summoner: Option<String>,
+  pub region: Option<String>,
+  /// Profile of the logged in account, `None` when profiles are shared
+  #[serde(default)]
+  pub profile: Option<String>,
+  /// Group whose config is currently linked as PersistedSettings.json
+  pub group: Option<String>,
+  pub linked_file: Option<PathBuf>,
+  #[serde(default)]
+  pub last_error: Option<LastError>,
+  /// Newer version downloaded and ready to install
+  #[serde(default)]
+  pub update_available: Option<String>
+}
+
+/// Most recent failure reported by the running instance
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LastError {
+  /// What was being done, like "reloading groups"
+  pub operation: String,
+  pub category: ErrorCategory,
+  pub message: String,
+  /// Seconds since the Unix epoch
+  pub time: u64
+}
+
+impl LastError {
+  pub fn new(operation: &str, error: &Error) -> LastError {
+    LastError {
+      operation: operation.to_owned(),
+      category: error.category(),
+      message: error.to_string(),
+      time: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+    }
+  }
+}
+
+struct State {
+  connection: ConnectionState,
+  summoner: Option<String>,
+  region: Option<String>,
+  profile: Option<String>,
+  last_error: Option<LastError>,
+  update_available: Option<String>
+}
+
+/// Status shared between the client and whoever reports it
+///
+/// The linked group isn't tracked, it's read back from the config folder so it
+/// can't drift from what's actually on disk.
+#[derive(Clone)]
+pub struct SharedStatus {
+  config_folder: PathBuf,
+  state: Arc<Mutex<State>>
+}
+
+impl SharedStatus {
+  pub fn new(config_folder: PathBuf) -> SharedStatus {
+    SharedStatus {
+      config_folder: config_folder,
+      state: Arc::new(Mutex::new(State {
+        connection: ConnectionState::Connecting,
+        summoner: None,
+        region: None,
+        profile: None,
+        last_error: None,
+        update_available: None
+      }))
+    }
+  }
+
+  pub fn set_connection(&self, connection: ConnectionState) {
+    self.state.lock().unwrap().connection = connection;
+  }
+
+  pub fn set_summoner(&self, summoner: Option<String>) {
+    self.state.lock().unwrap().summoner = summoner;
+  }
+
+  pub fn set_region(&self, region: Option<String>) {
+    self.state.lock().unwrap().region = region;
+  }
+
+  pub fn set_profile(&self, profile: Option<String>) {
+    self.state.lock().unwrap().profile = profile;
+  }
+
+  pub fn set_last_error(&self, last_error: LastError) {
+    self.state.lock().unwrap().last_error = Some(last_error);
+  }
+
+  pub fn set_update_available(&self, version: Option<String>) {
+    self.state.lock().unwrap().update_available = version;
+  }
+
+  pub fn snapshot(&self) -> Status {
+    let state = self.state.lock().unwrap();
+    let linked_file = self.config_folder.join("PersistedSettings.json").read_link().ok();
+
+    Status {
+      connection: state.connection,
+      summoner: state.summoner.clone(),
+      region: state.region.clone(),
+      profile: state.profile.clone(),
+      group: linked_file
+        .as_ref()
+        .and_then(|f| f.file_stem())
+        .map(|s| s.to_string_lossy().into_owned()),
+      linked_file,
+      last_error: state.last_error.clone(),
+      update_available: state.update_available.clone()
+    }
+  }
+}
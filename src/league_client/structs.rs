@@ -4,23 +4,35 @@ use serde_json::Value;
 #[serde(rename_all = "camelCase")]
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct Error {
-  error_code: String,
-  http_status: u16,
-  message: String
+  pub error_code: String,
+  pub http_status: u16,
+  pub message: String
 }
 
 #[serde(rename_all = "camelCase")]
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
 pub struct LocalSummoner {
-  display_name: String,
+  pub display_name: String,
   #[serde(deserialize_with = "to_str")]
   account_id: String,
   #[serde(deserialize_with = "to_str")]
   pub summoner_id: String,
+  #[serde(default)]
+  pub internal_name: String,
+  /// Riot account ID, left out by older clients
+  #[serde(default)]
+  pub puuid: String,
   summoner_level: u16,
   profile_icon_id: u32
 }
 
+impl LocalSummoner {
+  /// Identifies the account within its region, the PUUID when the client has one
+  pub fn account_key(&self) -> &str {
+    if self.puuid.is_empty() { &self.summoner_id } else { &self.puuid }
+  }
+}
+
 #[serde(rename_all = "camelCase")]
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct RSO {
@@ -32,7 +44,11 @@ pub struct RSO {
 pub struct TeamMember {
   #[serde(deserialize_with = "to_str")]
   pub summoner_id: String,
-  pub champion_id: i32
+  #[serde(default)]
+  pub cell_id: i64,
+  pub champion_id: i32,
+  #[serde(default)]
+  pub champion_pick_intent: i32
 }
 
 #[serde(rename_all = "camelCase")]
@@ -55,13 +71,132 @@ pub struct ChampionSelectTimer {
   pub phase: ChampionSelectTimerPhase
 }
 
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct ChampionSelectAction {
+  pub actor_cell_id: i64,
+  pub champion_id: i32,
+  pub completed: bool,
+  #[serde(rename = "type")]
+  pub action_type: String
+}
+
 #[serde(rename_all = "camelCase")]
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct ChampionSelectSessionUpdate {
+  #[serde(default)]
+  pub actions: Vec<Vec<ChampionSelectAction>>,
+  #[serde(default)]
+  pub local_player_cell_id: Option<i64>,
   pub my_team: Vec<TeamMember>,
   pub timer: ChampionSelectTimer
 }
 
+impl ChampionSelectSessionUpdate {
+  /// Finds the local player in `myTeam` by its cell, or by `summoner_id` for
+  /// sessions without `localPlayerCellId`
+  pub fn local_member(&self, summoner_id: &str) -> Option<&TeamMember> {
+    match self.local_player_cell_id {
+      Some(cell_id) => self.my_team.iter().find(|m| m.cell_id == cell_id),
+      None => self.my_team.iter().find(|m| m.summoner_id == summoner_id)
+    }
+  }
+
+  /// Champion the local player is locked into, if any
+  ///
+  /// Works for every queue type: draft and blind pick report it through a
+  /// completed pick action, while modes without pick actions (ARAM, some
+  /// custom and rotating modes) assign `championId` directly
+  pub fn locked_champion(&self, summoner_id: &str) -> Option<i32> {
+    let member = self.local_member(summoner_id)?;
+
+    let picks: Vec<&ChampionSelectAction> = self
+      .actions
+      .iter()
+      .flat_map(|turn| turn.iter())
+      .filter(|a| a.actor_cell_id == member.cell_id && a.action_type == "pick")
+      .collect();
+
+    let champion_id = if picks.is_empty() || self.timer.phase == ChampionSelectTimerPhase::Finalization {
+      member.champion_id
+    } else {
+      match picks.iter().rev().find(|a| a.completed) {
+        Some(pick) if pick.champion_id > 0 => pick.champion_id,
+        Some(_) => member.champion_id,
+        None => return None
+      }
+    };
+
+    if champion_id > 0 {
+      Some(champion_id)
+    } else {
+      None
+    }
+  }
+
+  /// Champion the local player is currently hovering, if any
+  pub fn hovered_champion(&self, summoner_id: &str) -> Option<i32> {
+    self
+      .local_member(summoner_id)
+      .map(|m| m.champion_pick_intent)
+      .filter(|id| *id > 0)
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GameflowPhase {
+  ChampSelect,
+  GameStart,
+  InProgress,
+  Reconnect,
+  Other
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct PlayerChampionSelection {
+  pub champion_id: i32,
+  #[serde(default)]
+  pub summoner_internal_name: String
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct GameflowGameData {
+  #[serde(default)]
+  pub is_custom_game: bool,
+  #[serde(default)]
+  pub player_champion_selections: Vec<PlayerChampionSelection>
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct GameflowSession {
+  pub phase: GameflowPhase,
+  #[serde(default)]
+  pub game_data: GameflowGameData
+}
+
+impl GameflowSession {
+  /// Champion the gameflow assigned to the local player, if any
+  pub fn local_champion(&self, internal_name: &str) -> Option<i32> {
+    self
+      .game_data
+      .player_champion_selections
+      .iter()
+      .find(|s| s.summoner_internal_name == internal_name)
+      .map(|s| s.champion_id)
+      .filter(|id| *id > 0)
+  }
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct LivePlayer {
+  pub champion_name: String,
+  pub summoner_name: String
+}
+
 #[serde(rename_all = "camelCase")]
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct InnerSettingsFile {
@@ -115,3 +250,19 @@ impl<'de> Deserialize<'de> for ChampionSelectTimerPhase {
     }
   }
 }
+
+impl<'de> Deserialize<'de> for GameflowPhase {
+  fn deserialize<D>(de: D) -> Result<GameflowPhase, D::Error>
+    where D: Deserializer<'de> {
+    let deser_result: Value = Deserialize::deserialize(de)?;
+
+    // Phases added by client updates mustn't stop the session from being read
+    Ok(match deser_result.as_str() {
+      Some("ChampSelect") => GameflowPhase::ChampSelect,
+      Some("GameStart") => GameflowPhase::GameStart,
+      Some("InProgress") => GameflowPhase::InProgress,
+      Some("Reconnect") => GameflowPhase::Reconnect,
+      _ => GameflowPhase::Other
+    })
+  }
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+/// Follows the local player's champion across champ select session updates
+///
+/// ARAM rerolls and bench trades can change the champion several times in a
+/// few seconds, so a new champion only becomes due once it has been stable for
+/// `debounce`, and nothing is due while it matches the one already applied
+pub struct ChampionTracker {
+  debounce: Duration,
+  applied: Option<i32>,
+  desired: Option<i32>,
+  changed_at: Option<Instant>
+}
+
+impl ChampionTracker {
+  pub fn new(debounce: Duration) -> ChampionTracker {
+    ChampionTracker {
+      debounce: debounce,
+      applied: None,
+      desired: None,
+      changed_at: None
+    }
+  }
+
+  pub fn debounce(&self) -> Duration {
+    self.debounce
+  }
+
+  pub fn set_debounce(&mut self, debounce: Duration) {
+    self.debounce = debounce;
+  }
+
+  /// Forgets everything, used when a new champ select starts
+  pub fn reset(&mut self) {
+    self.applied = None;
+    self.desired = None;
+    self.changed_at = None;
+  }
+
+  /// Records the champion seen in the latest update
+  pub fn observe(&mut self, champion_id: i32, now: Instant) {
+    if self.desired != Some(champion_id) {
+      self.desired = Some(champion_id);
+      self.changed_at = Some(now);
+    }
+  }
+
+  /// Champion that should be applied now, if it differs from the applied one
+  /// and has been stable for long enough
+  pub fn due(&self, now: Instant) -> Option<i32> {
+    match (self.desired, self.changed_at) {
+      (Some(id), Some(at)) if self.applied != Some(id) && now.duration_since(at) >= self.debounce => Some(id),
+      _ => None
+    }
+  }
+
+  /// Champion that should be applied now, ignoring the debounce window
+  pub fn pending(&self) -> Option<i32> {
+    match self.desired {
+      Some(id) if self.applied != Some(id) => Some(id),
+      _ => None
+    }
+  }
+
+  /// Marks a champion as applied, or at least attempted
+  pub fn applied(&mut self, champion_id: i32) {
+    self.applied = Some(champion_id);
+  }
+}
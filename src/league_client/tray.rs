@@ -0,0 +1,254 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use websocket::futures::sync::mpsc::UnboundedSender;
+
+use systray::{Application, SystrayEvent};
+
+use errors::*;
+use league_client::client::LeagueClientFn;
+use league_client::config::ConfigFolder;
+use league_client::editor;
+use league_client::notify::{Notification, Notifier};
+use league_client::settings::Settings;
+use league_client::status::{SharedStatus, Status};
+use league_client::tray_menu::{menu_state, state_entries, status_summary, MenuAction, MenuEntry};
+
+static ICON: &'static [u8] = include_bytes!("../../resources/dark-binding.ico");
+
+/// A running system tray icon, removed by `quit`
+///
+/// The tray can't change a menu once it's shown, so the icon is replaced with a
+/// new one when the connection, the summoner, the group in use, the groups or
+/// the update offered change. The profile and last error are shown by the
+/// menu's "Show status".
+pub struct Tray {
+  current: Arc<Mutex<Option<Sender<SystrayEvent>>>>
+}
+
+struct MenuContext {
+  dir: PathBuf,
+  editor_url: String,
+  config: ConfigFolder,
+  status: SharedStatus,
+  client_sender: UnboundedSender<LeagueClientFn>
+}
+
+impl Tray {
+  /// Shows the tray icon and its menu for `config`, every item is forwarded to
+  /// the client through `client_sender`
+  pub fn show(
+    config: ConfigFolder,
+    editor_url: &str,
+    status: SharedStatus,
+    client_sender: UnboundedSender<LeagueClientFn>
+  ) -> Result<Tray> {
+    let context = MenuContext {
+      dir: config.data_dir(),
+      editor_url: editor_url.to_owned(),
+      config: config,
+      status: status.clone(),
+      client_sender: client_sender
+    };
+
+    let current = Arc::new(Mutex::new(None));
+    let tray_current = current.clone();
+    let (shown_sender, shown) = channel();
+
+    thread::spawn(move || {
+      let groups = context.group_names().unwrap_or_default();
+      let mut shown_state = menu_state(&status.snapshot(), &groups);
+
+      match context.build(&status.snapshot(), &groups) {
+        Ok(sender) => {
+          *tray_current.lock().unwrap() = Some(sender);
+          shown_sender.send(Ok(())).ok();
+        }
+        Err(e) => {
+          shown_sender.send(Err(e)).ok();
+          return;
+        }
+      }
+
+      loop {
+        // How often the menu is checked against the client's state
+        thread::sleep(Duration::from_secs(1));
+
+        // A groups.toml being edited keeps the groups already shown
+        let groups = context.group_names().unwrap_or_else(|| shown_state.groups.clone());
+        let snapshot = status.snapshot();
+        let state = menu_state(&snapshot, &groups);
+
+        if state == shown_state {
+          continue;
+        }
+
+        let mut current = tray_current.lock().unwrap();
+
+        // Quit from the menu or from `Tray::quit`
+        let old = match current.take() {
+          Some(old) => old,
+          None => return
+        };
+
+        old.send(SystrayEvent::Quit).ok();
+
+        match context.build(&snapshot, &groups) {
+          Ok(sender) => *current = Some(sender),
+          Err(e) => {
+            error!("op=refresh_tray outcome=error error={:?}", e.to_string());
+            return;
+          }
+        }
+
+        shown_state = state;
+      }
+    });
+
+    shown
+      .recv()
+      .unwrap_or_else(|_| Err(ErrorKind::TrayUnavailable("tray thread stopped".to_owned()).into()))?;
+
+    Ok(Tray { current: current })
+  }
+
+  pub fn quit(self) {
+    if let Some(sender) = self.current.lock().unwrap().take() {
+      sender.send(SystrayEvent::Quit).ok();
+    }
+  }
+}
+
+impl MenuContext {
+  /// Group names from groups.toml, if it can be read
+  fn group_names(&self) -> Option<Vec<String>> {
+    self
+      .config
+      .groups()
+      .map(|groups| groups.groups.keys().cloned().collect())
+      .ok()
+  }
+
+  /// Shows a tray icon whose menu reflects `status` and `groups`
+  fn build(&self, status: &Status, groups: &[String]) -> Result<Sender<SystrayEvent>> {
+    let mut tray = Application::new().map_err(|e| ErrorKind::TrayUnavailable(format!("{:?}", e)))?;
+
+    tray
+      .set_icon_from_buffer(ICON, 64, 64)
+      .map_err(|e| ErrorKind::TrayUnavailable(format!("{:?}", e)))?;
+
+    for entry in state_entries(status, groups) {
+      match entry {
+        MenuEntry::Label(label) => {
+          tray.add_menu_item(&label, |_| {}).ok();
+        }
+        MenuEntry::Action(label, action) => {
+          let dir = self.dir.clone();
+          let config = self.config.clone();
+          let status = self.status.clone();
+          let sender = self.client_sender.clone();
+
+          tray
+            .add_menu_item(&label, move |_| {
+              let command = match action {
+                MenuAction::ShowStatus => {
+                  Notifier::default().notify(Notification::Status(status_summary(&status.snapshot())));
+                  return;
+                }
+                MenuAction::ApplyGroup(ref group) => LeagueClientFn::ApplyGroup(group.to_owned()),
+                MenuAction::ResetGroup(ref group) => LeagueClientFn::ResetGroup(group.to_owned()),
+                MenuAction::InstallUpdate(ref version) => LeagueClientFn::InstallUpdate(version.to_owned()),
+                MenuAction::OpenGroupConfig(ref group) => {
+                  open_in_editor(&dir, &config.group_config(group));
+                  return;
+                }
+              };
+
+              sender.unbounded_send(command).ok();
+            })
+            .ok();
+        }
+        MenuEntry::Separator => {
+          tray.add_menu_separator().ok();
+        }
+      }
+    }
+
+    let editor_url = self.editor_url.clone();
+
+    tray
+      .add_menu_item(&"Edit Champion Groups".to_owned(), move |_| {
+        if let Err(e) = editor::open_default(&editor_url) {
+          error!("op=open_groups_editor url={:?} outcome=error error={:?}", editor_url, e.to_string());
+        }
+      })
+      .ok();
+
+    // Both files are reloaded by the watcher once saved, whatever the editor does
+    let (dir, config) = (self.dir.clone(), self.config.clone());
+
+    tray
+      .add_menu_item(&"Edit groups.toml".to_owned(), move |_| open_in_editor(&dir, &config.groups_toml()))
+      .ok();
+
+    let dir = self.dir.clone();
+
+    tray
+      .add_menu_item(&"Edit settings.toml".to_owned(), move |_| open_in_editor(&dir, &dir.join("settings.toml")))
+      .ok();
+
+    let logs_dir = self.config.logs_dir();
+
+    tray
+      .add_menu_item(&"Open log folder".to_owned(), move |_| {
+        if let Err(e) = editor::open_default(&logs_dir.to_string_lossy()) {
+          error!("op=open_log_folder path={:?} outcome=error error={:?}", logs_dir, e.to_string());
+        }
+      })
+      .ok();
+
+    let sender_1 = self.client_sender.clone();
+
+    tray
+      .add_menu_item(&"Backup Config".to_string(), move |_| {
+        sender_1
+          .unbounded_send(LeagueClientFn::BackupConfig)
+          .expect("Couldn't backup config");
+      })
+      .ok();
+
+    let sender_2 = self.client_sender.clone();
+
+    tray
+      .add_menu_item(&"Restore Config".to_string(), move |_| {
+        sender_2
+          .unbounded_send(LeagueClientFn::RestoreConfig)
+          .expect("Couldn't restore config");
+      })
+      .ok();
+
+    tray.add_menu_separator().ok();
+
+    let sender_3 = self.client_sender.clone();
+
+    tray
+      .add_menu_item(&"Quit".to_string(), move |sender| {
+        sender_3.unbounded_send(LeagueClientFn::Shutdown).unwrap();
+        sender.send(SystrayEvent::Quit).ok();
+      })
+      .ok();
+
+    Ok(tray.wait_for_message())
+  }
+}
+
+/// Opens `path` with the editor from the settings in `dir`
+fn open_in_editor(dir: &Path, path: &Path) {
+  let editor = Settings::read(dir).unwrap_or_default().editor;
+
+  if let Err(e) = editor::open(editor.as_ref().map(|e| &e[..]), path) {
+    error!("op=open_editor path={:?} outcome=error error={:?}", path, e.to_string());
+  }
+}
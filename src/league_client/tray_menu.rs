@@ -0,0 +1,186 @@
+use league_client::status::{ConnectionState, Status};
+
+/// What a group item of the tray menu does
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuAction {
+  ShowStatus,
+  ApplyGroup(String),
+  OpenGroupConfig(String),
+  ResetGroup(String),
+  InstallUpdate(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuEntry {
+  /// Informational item, does nothing when clicked
+  Label(String),
+  Action(String, MenuAction),
+  Separator
+}
+
+/// Everything the menu entries depend on, the icon is only rebuilt when it changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuState {
+  pub connection: String,
+  pub group: Option<String>,
+  pub update_available: Option<String>,
+  pub groups: Vec<String>
+}
+
+pub fn menu_state(status: &Status, groups: &[String]) -> MenuState {
+  MenuState {
+    connection: connection_label(status),
+    group: status.group.clone(),
+    update_available: status.update_available.clone(),
+    groups: groups.to_vec()
+  }
+}
+
+/// Connection and summoner, as shown at the top of the menu
+fn connection_label(status: &Status) -> String {
+  match status.connection {
+    ConnectionState::Connecting => "Connecting to the League client".to_owned(),
+    ConnectionState::Disconnected => "Disconnected from the League client".to_owned(),
+    ConnectionState::Connected => format!(
+      "Connected as {} ({})",
+      status.summoner.as_ref().map_or("unknown summoner", |s| &s[..]),
+      status.region.as_ref().map_or("unknown region", |r| &r[..])
+    )
+  }
+}
+
+fn linked_label(status: &Status) -> String {
+  match status.group {
+    Some(ref group) => format!("Bindings of {} in use", group),
+    None => "Base bindings in use".to_owned()
+  }
+}
+
+/// State and group items shown above the static ones
+///
+/// The tray can't nest menus, so every group gets its own indented actions.
+/// The profile and last error aren't worth rebuilding the menu for, they are
+/// left to "Show status".
+pub fn state_entries(status: &Status, groups: &[String]) -> Vec<MenuEntry> {
+  let mut entries = vec![
+    MenuEntry::Label(connection_label(status)),
+    MenuEntry::Label(linked_label(status)),
+    MenuEntry::Action("Show status".to_owned(), MenuAction::ShowStatus)
+  ];
+
+  if let Some(ref version) = status.update_available {
+    entries.push(MenuEntry::Action(
+      format!("Install version {}", version),
+      MenuAction::InstallUpdate(version.to_owned())
+    ));
+  }
+
+  entries.push(MenuEntry::Separator);
+
+  if groups.is_empty() {
+    entries.push(MenuEntry::Label("No champion groups yet".to_owned()));
+  }
+
+  for group in groups {
+    let title = if status.group.as_ref() == Some(group) {
+      format!("{} (in use)", group)
+    } else {
+      group.to_owned()
+    };
+
+    entries.push(MenuEntry::Label(title));
+    entries.push(MenuEntry::Action("    Apply now".to_owned(), MenuAction::ApplyGroup(group.to_owned())));
+    entries.push(MenuEntry::Action("    Open config".to_owned(), MenuAction::OpenGroupConfig(group.to_owned())));
+    entries.push(MenuEntry::Action("    Reset to base".to_owned(), MenuAction::ResetGroup(group.to_owned())));
+  }
+
+  entries.push(MenuEntry::Separator);
+
+  entries
+}
+
+/// Connection, bindings in use, profile and last error, one per line
+pub fn status_summary(status: &Status) -> String {
+  let mut lines = vec![connection_label(status), linked_label(status)];
+
+  if let Some(ref profile) = status.profile {
+    lines.push(format!("Groups of profile {}", profile));
+  }
+
+  if let Some(ref e) = status.last_error {
+    lines.push(format!("Last error while {}: {}", e.operation, e.message));
+  }
+
+  lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn status() -> Status {
+    Status {
+      connection: ConnectionState::Connected,
+      summoner: Some("tester".to_owned()),
+      region: Some("EUW1".to_owned()),
+      profile: None,
+      group: Some("adc".to_owned()),
+      linked_file: None,
+      last_error: None,
+      update_available: None
+    }
+  }
+
+  #[test]
+  /// The connected summoner and the group in use are shown
+  fn shows_connection_and_linked_group() {
+    assert_eq!(status_summary(&status()), "Connected as tester (EUW1)\nBindings of adc in use");
+  }
+
+  #[test]
+  /// The menu shows the connection, the summoner and the group in use
+  fn shows_state_in_menu() {
+    let entries = state_entries(&status(), &["adc".to_owned(), "mages".to_owned()]);
+
+    assert_eq!(entries[0], MenuEntry::Label("Connected as tester (EUW1)".to_owned()));
+    assert_eq!(entries[1], MenuEntry::Label("Bindings of adc in use".to_owned()));
+    assert!(entries.contains(&MenuEntry::Label("adc (in use)".to_owned())));
+    assert!(entries.contains(&MenuEntry::Action(
+      "    Apply now".to_owned(),
+      MenuAction::ApplyGroup("mages".to_owned())
+    )));
+  }
+
+  #[test]
+  /// Swaps, reconnections and group changes rebuild the menu, a new profile doesn't
+  fn menu_follows_state() {
+    let groups = ["adc".to_owned(), "mages".to_owned()];
+    let shown = menu_state(&status(), &groups);
+
+    let disconnected = Status {
+      connection: ConnectionState::Disconnected,
+      ..status()
+    };
+    let other_summoner = Status {
+      summoner: Some("someone".to_owned()),
+      ..status()
+    };
+    let swapped = Status {
+      group: Some("mages".to_owned()),
+      ..status()
+    };
+
+    assert!(menu_state(&disconnected, &groups) != shown);
+    assert!(menu_state(&other_summoner, &groups) != shown);
+    assert!(menu_state(&swapped, &groups) != shown);
+    assert!(menu_state(&status(), &groups[..1]) != shown);
+    assert_eq!(state_entries(&swapped, &groups)[1], MenuEntry::Label("Bindings of mages in use".to_owned()));
+
+    let other_profile = Status {
+      profile: Some("EUW1-1".to_owned()),
+      ..status()
+    };
+
+    assert_eq!(menu_state(&other_profile, &groups), shown);
+  }
+}
@@ -2,16 +2,11 @@ use std::{fs, io};
 use std::io::prelude::*;
 use std::fs::{File, OpenOptions};
 use std::path::Path;
-use reqwest::{Response, Url};
 use regex::Regex;
 use std::process::{Command, Stdio};
-use std::borrow::Borrow;
-use serde::ser::Serialize;
 use serde_json;
 use toml;
 
-use HTTP_CLIENT;
-
 use league_client::*;
 use league_client::structs::PersistedSettings;
 
@@ -71,6 +66,7 @@ pub fn parse_credentials(str_to_match: String) -> Result<(Credentials, String)>
   )
 }
 
+#[cfg(not(target_os = "linux"))]
 pub fn find_client() -> Result<(Credentials, String)> {
   let child = Command::new("WMIC")
     .args(
@@ -94,65 +90,44 @@ pub fn find_client() -> Result<(Credentials, String)> {
   Ok(parse_credentials(output)?)
 }
 
-pub fn build_uri<I, K, V>(endpoint: &str,
-                          credentials: &Credentials,
-                          query: Option<I>)
-                          -> Result<Url>
-  where I: IntoIterator,
-        I::Item: Borrow<(K, V)>,
-        K: AsRef<str>,
-        V: AsRef<str> {
-  let uri = match endpoint.starts_with("/") {
-    true => format!("https://127.0.0.1:{}{}", credentials.port, endpoint),
-    false => format!("https://127.0.0.1:{}/{}", credentials.port, endpoint),
-  };
-
-  if let Some(opts) = query {
-    return Ok(Url::parse_with_params(&uri, opts)?);
+/// Looks for the client running under Wine, quoting its arguments like WMIC does
+#[cfg(target_os = "linux")]
+pub fn find_client() -> Result<(Credentials, String)> {
+  for entry in fs::read_dir("/proc")? {
+    let cmdline = match fs::read(entry?.path().join("cmdline")) {
+      Ok(cmdline) => cmdline,
+      _ => continue
+    };
+
+    let args: Vec<String> = cmdline
+      .split(|b| *b == 0)
+      .map(|arg| format!("\"{}\"", String::from_utf8_lossy(arg)))
+      .collect();
+
+    if args.iter().any(|arg| arg.contains("LeagueClientUx.exe")) {
+      return parse_credentials(args.join(" "));
+    }
   }
 
-  Ok(Url::parse(&uri)?)
-}
-
-pub fn base_get<I, K, V>(endpoint: &str,
-                         credentials: &Credentials,
-                         query: Option<I>)
-                         -> Result<Response>
-  where I: IntoIterator,
-        I::Item: Borrow<(K, V)>,
-        K: AsRef<str>,
-        V: AsRef<str> {
-  let uri = build_uri(endpoint, credentials, query)?;
-
-  Ok(
-    HTTP_CLIENT
-      .get(uri)
-      .basic_auth("riot", Some(credentials.token.to_owned()))
-      .send()?
-  )
+  bail!("couldn't find a running LeagueClientUx.exe")
 }
 
-// pub fn base_post<T: Serialize>(endpoint: &str,
-//                                credentials: &Credentials,
-//                                json: &T)
-//                                -> Result<Response> {
-//   let uri: Url = build_uri(endpoint, credentials, None::<&[(String, String)]>)?;
-
-//   Ok(
-//     HTTP_CLIENT
-//       .post(uri)
-//       .json(json)
-//       .basic_auth("riot", Some(credentials.token.to_owned()))
-//       .send()?
-//   )
-// }
-
 pub fn normalize_champion_name(name: &str) -> String {
   NAME_SPECIAL_REGEX.replace(&name.to_uppercase(), "").into_owned()
 }
 
 pub fn is_symlink(path: &Path) -> Result<bool> {
-  Ok(fs::metadata(path)?.file_type().is_symlink())
+  Ok(fs::symlink_metadata(path)?.file_type().is_symlink())
+}
+
+/// Creates a symbolic link at `dst` pointing to the file at `src`
+pub fn symlink_file(src: &Path, dst: &Path) -> Result<()> {
+  #[cfg(windows)]
+  ::std::os::windows::fs::symlink_file(src, dst)?;
+  #[cfg(unix)]
+  ::std::os::unix::fs::symlink(src, dst)?;
+
+  Ok(())
 }
 
 pub fn ensure_dir(path: &Path) -> Result<()> {
@@ -175,28 +150,53 @@ pub fn ensure_dir(path: &Path) -> Result<()> {
   Ok(())
 }
 
+/// Recursively copies the contents of `src` into `dst`
+pub fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+  fs::create_dir_all(dst)?;
+
+  for entry in fs::read_dir(src)? {
+    let entry = entry?;
+    let target = dst.join(entry.file_name());
+
+    if entry.file_type()?.is_dir() {
+      copy_dir(&entry.path(), &target)?;
+    } else {
+      fs::copy(entry.path(), target)?;
+    }
+  }
+
+  Ok(())
+}
+
 pub fn read_settings_json(path: &Path) -> Result<PersistedSettings> {
-  let mut s = String::new();
-  File::open(path)
-    .unwrap()
-    .read_to_string(&mut s)
-    .unwrap();
+  let s = read_file(path)?;
 
-  Ok(serde_json::from_str(&s)?)
+  Ok(serde_json::from_str(&s).chain_err(|| ErrorKind::Parse(format!("{:?}", path)))?)
 }
 
 pub fn read_toml(path: &Path) -> Result<GroupsToml> {
+  let s = read_file(path)?;
+
+  Ok(toml::from_str(&s).chain_err(|| ErrorKind::Parse(format!("{:?}", path)))?)
+}
+
+fn read_file(path: &Path) -> Result<String> {
   let mut s = String::new();
+
   File::open(path)
-    .unwrap()
-    .read_to_string(&mut s)
-    .unwrap();
+    .and_then(|mut f| f.read_to_string(&mut s))
+    .chain_err(|| ErrorKind::ConfigIo("read".to_owned(), path.to_owned()))?;
 
-  Ok(toml::from_str(&s)?)
+  Ok(s)
 }
 
 pub fn write_settings_json(path: &Path, content: &PersistedSettings) -> Result<()> {
-  let file = OpenOptions::new().write(true).create(true).open(path)?;
+  let file = OpenOptions::new()
+    .write(true)
+    .create(true)
+    .truncate(true)
+    .open(path)
+    .chain_err(|| ErrorKind::ConfigIo("write".to_owned(), path.to_owned()))?;
 
   Ok(serde_json::to_writer_pretty(file, content)?)
 }
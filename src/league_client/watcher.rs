@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use websocket::futures::sync::mpsc::UnboundedSender;
+
+use errors::*;
+
+use league_client::client::LeagueClientFn;
+use league_client::config::ConfigFolder;
+use league_client::settings::Settings;
+
+/// Reloads groups.toml and settings.toml whenever they change on disk
+///
+/// Editors that fork or keep running can't tell us when they're done, so the
+/// files are polled instead. A file is only reloaded once it parses, a broken
+/// edit keeps the previous groups or settings. Stops when dropped.
+pub struct Watcher {
+  stop: Arc<AtomicBool>
+}
+
+impl Drop for Watcher {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::SeqCst);
+  }
+}
+
+pub fn watch(config: ConfigFolder, sender: UnboundedSender<LeagueClientFn>, interval: Duration) -> Watcher {
+  let stop = Arc::new(AtomicBool::new(false));
+  let stopped = stop.clone();
+
+  thread::spawn(move || {
+    let mut poller = Poller::new(config, sender);
+
+    while !stopped.load(Ordering::SeqCst) {
+      thread::sleep(interval);
+      poller.poll();
+    }
+  });
+
+  Watcher { stop: stop }
+}
+
+/// Last seen contents of the watched files, compared on every poll
+///
+/// Contents rather than modification times, which some filesystems only keep
+/// to the second.
+struct Poller {
+  config: ConfigFolder,
+  sender: UnboundedSender<LeagueClientFn>,
+  groups_toml: PathBuf,
+  groups: Option<Vec<u8>>,
+  settings: Option<Vec<u8>>
+}
+
+impl Poller {
+  fn new(config: ConfigFolder, sender: UnboundedSender<LeagueClientFn>) -> Poller {
+    let groups_toml = config.groups_toml();
+
+    Poller {
+      groups: contents(&groups_toml),
+      settings: contents(&config.data_dir().join("settings.toml")),
+      groups_toml: groups_toml,
+      config: config,
+      sender: sender
+    }
+  }
+
+  fn poll(&mut self) {
+    // The account changed, the switch loads the new profile's groups itself
+    if self.config.groups_toml() != self.groups_toml {
+      self.groups_toml = self.config.groups_toml();
+      self.groups = contents(&self.groups_toml);
+    }
+
+    if changed(&self.groups_toml, &mut self.groups) {
+      let valid = self.config.groups();
+
+      reload(&self.groups_toml, valid.map(|_| LeagueClientFn::ReloadGroups), &self.sender);
+    }
+
+    let settings_toml = self.config.data_dir().join("settings.toml");
+
+    if changed(&settings_toml, &mut self.settings) {
+      let valid = Settings::read(&self.config.data_dir());
+
+      reload(&settings_toml, valid.map(|_| LeagueClientFn::ReloadSettings), &self.sender);
+    }
+  }
+}
+
+fn contents(path: &Path) -> Option<Vec<u8>> {
+  fs::read(path).ok()
+}
+
+fn changed(path: &Path, last: &mut Option<Vec<u8>>) -> bool {
+  let current = contents(path);
+
+  if current == *last {
+    return false;
+  }
+
+  *last = current;
+
+  last.is_some()
+}
+
+fn reload(path: &Path, command: Result<LeagueClientFn>, sender: &UnboundedSender<LeagueClientFn>) {
+  match command {
+    Ok(command) => {
+      debug!("{:?} changed, reloading", path);
+      sender.unbounded_send(command).ok();
+    }
+    Err(e) => warn!("op=reload path={:?} outcome=error error={:?}", path, e.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::File;
+  use std::io::Write;
+  use tempdir::TempDir;
+  use websocket::futures::Stream;
+  use websocket::futures::sync::mpsc::unbounded;
+
+  use super::*;
+
+  #[test]
+  /// Edits that parse are reloaded, broken ones are skipped
+  fn reloads_valid_groups() {
+    let dir = TempDir::new("dark-binding-watcher").unwrap();
+    let config = ConfigFolder::new(dir.path().to_owned());
+    let (sender, rx) = unbounded();
+
+    fs::create_dir_all(config.data_dir()).unwrap();
+    File::create(config.groups_toml()).unwrap().write_all(b"[groups]\n").unwrap();
+
+    let mut poller = Poller::new(config.clone(), sender);
+
+    poller.poll();
+    File::create(config.groups_toml()).unwrap().write_all(b"[groups\n").unwrap();
+    poller.poll();
+    File::create(config.groups_toml()).unwrap().write_all(b"[groups]\nadc = [\"Jinx\"]\n").unwrap();
+    poller.poll();
+    poller.poll();
+
+    // Dropping the poller drops its sender, which ends the stream
+    drop(poller);
+    let reloads: Vec<_> = rx.wait().collect();
+
+    assert_eq!(reloads.len(), 1);
+    match reloads[0] {
+      Ok(LeagueClientFn::ReloadGroups) => {}
+      _ => panic!("expected a groups reload")
+    }
+
+    assert_eq!(config.groups().unwrap().groups.len(), 1);
+  }
+}
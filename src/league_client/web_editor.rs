@@ -0,0 +1,322 @@
+//! Groups editor served on 127.0.0.1 for people who'd rather not edit TOML
+//!
+//! Every request must carry the random token the page was opened with, as a
+//! `token` query parameter for the page and an `X-Token` header for the API, so
+//! other local programs and web pages can't rewrite the groups. Both are checked
+//! before a body is read, and bodies are capped at `MAX_BODY` bytes.
+//!
+//! Saving writes groups.toml anew, the page tells its comments and group order
+//! aren't kept.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use rand::{thread_rng, Rng};
+use serde_json::{self, Value};
+use websocket::futures::sync::mpsc::UnboundedSender;
+
+use errors::*;
+
+use league_client::client::LeagueClientFn;
+use league_client::config::ConfigFolder;
+use league_client::structs::GroupsToml;
+
+static PAGE: &'static str = include_str!("./web_editor.html");
+
+/// Largest request body accepted, far above any groups.toml
+const MAX_BODY: usize = 256 * 1024;
+
+/// Connections are served one at a time, a silent one mustn't hold the others
+const TIMEOUT_SECS: u64 = 5;
+
+/// A running editor server
+pub struct WebEditor {
+  url: String
+}
+
+impl WebEditor {
+  /// Serves the editor for `config`, saved groups are reloaded through `sender`
+  pub fn serve(config: ConfigFolder, sender: UnboundedSender<LeagueClientFn>) -> Result<WebEditor> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let token: String = thread_rng().gen_ascii_chars().take(32).collect();
+
+    let handler = Handler {
+      config: config,
+      sender: sender,
+      token: token.clone(),
+      host: format!("127.0.0.1:{}", port)
+    };
+
+    thread::spawn(move || for stream in listener.incoming() {
+      if let Ok(stream) = stream {
+        handler.handle(stream).ok();
+      }
+    });
+
+    Ok(WebEditor {
+      url: format!("http://127.0.0.1:{}/?token={}", port, token)
+    })
+  }
+
+  pub fn url(&self) -> &str {
+    &self.url
+  }
+}
+
+/// Request line and headers, the body is left in the stream until accepted
+struct Request {
+  method: String,
+  path: String,
+  host: Option<String>,
+  token: Option<String>,
+  content_length: usize
+}
+
+struct Handler {
+  config: ConfigFolder,
+  sender: UnboundedSender<LeagueClientFn>,
+  token: String,
+  host: String
+}
+
+impl Handler {
+  fn handle(&self, stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(TIMEOUT_SECS)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(TIMEOUT_SECS)))?;
+
+    let mut reader = BufReader::new(stream);
+    let request = read_request(&mut reader)?;
+
+    // A different host means the page was reached through DNS rebinding
+    if request.host.as_ref() != Some(&self.host) {
+      return respond(reader.get_mut(), 403, "text/plain", b"Forbidden");
+    }
+
+    let (path, query) = match request.path.find('?') {
+      Some(i) => (&request.path[..i], &request.path[i + 1..]),
+      None => (&request.path[..], "")
+    };
+
+    match (&request.method[..], path) {
+      ("GET", "/") if query.split('&').any(|p| p == format!("token={}", self.token)) => {
+        respond(reader.get_mut(), 200, "text/html; charset=utf-8", PAGE.as_bytes())
+      }
+      (_, "/api/groups") if request.token.as_ref() == Some(&self.token) => {
+        if request.content_length > MAX_BODY {
+          let body = json!({ "errors": ["request too large"] });
+
+          return respond(reader.get_mut(), 413, "application/json", body.to_string().as_bytes());
+        }
+
+        let mut body = vec![0; request.content_length];
+        reader.read_exact(&mut body)?;
+
+        let (status, body) = match &request.method[..] {
+          "GET" => self.groups(),
+          "PUT" => self.save_groups(&body),
+          _ => (405, json!({ "errors": ["method not allowed"] }))
+        };
+
+        respond(reader.get_mut(), status, "application/json", body.to_string().as_bytes())
+      }
+      _ => respond(reader.get_mut(), 403, "text/plain", b"Forbidden")
+    }
+  }
+
+  fn groups(&self) -> (u16, Value) {
+    let result = self.config.groups().and_then(|groups| {
+      let champions: Vec<String> = self
+        .config
+        .champion_aliases()?
+        .map(|aliases| aliases.into_iter().map(|(alias, _)| alias).collect())
+        .unwrap_or_default();
+
+      Ok(json!({ "groups": groups.groups, "champions": champions }))
+    });
+
+    match result {
+      Ok(body) => (200, body),
+      Err(e) => (500, json!({ "errors": [e.to_string()] }))
+    }
+  }
+
+  fn save_groups(&self, body: &[u8]) -> (u16, Value) {
+    let groups: GroupsToml = match serde_json::from_slice(body) {
+      Ok(groups) => groups,
+      Err(e) => return (400, json!({ "errors": [e.to_string()] }))
+    };
+
+    let result = self
+      .config
+      .champion_catalog()
+      .and_then(|catalog| self.config.save_groups(&groups, catalog.as_ref()));
+
+    match result {
+      Ok(_) => {
+        self.sender.unbounded_send(LeagueClientFn::ReloadGroups).ok();
+
+        (200, json!({ "groups": groups.groups }))
+      }
+      Err(Error(ErrorKind::InvalidGroups(problems), _)) => (422, json!({ "errors": problems })),
+      Err(e) => (500, json!({ "errors": [e.to_string()] }))
+    }
+  }
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request> {
+  let mut line = String::new();
+  reader.read_line(&mut line)?;
+
+  let mut parts = line.split_whitespace();
+  let method = parts.next().unwrap_or("").to_owned();
+  let path = parts.next().unwrap_or("").to_owned();
+
+  let mut host = None;
+  let mut token = None;
+  let mut content_length = 0;
+
+  loop {
+    line.clear();
+
+    if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+      break;
+    }
+
+    if let Some(i) = line.find(':') {
+      let value = line[i + 1..].trim().to_owned();
+
+      match &line[..i].to_lowercase()[..] {
+        "host" => host = Some(value),
+        "x-token" => token = Some(value),
+        "content-length" => content_length = value.parse()?,
+        _ => {}
+      }
+    }
+  }
+
+  Ok(Request {
+    method: method,
+    path: path,
+    host: host,
+    token: token,
+    content_length: content_length
+  })
+}
+
+fn respond(stream: &mut TcpStream, status: u16, content_type: &str, body: &[u8]) -> Result<()> {
+  let reason = match status {
+    200 => "OK",
+    400 => "Bad Request",
+    403 => "Forbidden",
+    405 => "Method Not Allowed",
+    413 => "Payload Too Large",
+    422 => "Unprocessable Entity",
+    _ => "Internal Server Error"
+  };
+
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+    status,
+    reason,
+    content_type,
+    body.len()
+  )?;
+  stream.write_all(body)?;
+
+  Ok(stream.flush()?)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+  use tempdir::TempDir;
+  use websocket::futures::sync::mpsc::unbounded;
+
+  use super::*;
+
+  /// Sends a raw request to the editor and returns the status code and body
+  fn request(editor: &WebEditor, method: &str, token: &str, body: &str) -> (u16, String) {
+    let host = editor.url()["http://".len()..].split('/').next().unwrap();
+    let mut stream = TcpStream::connect(host).unwrap();
+
+    write!(
+      stream,
+      "{} /api/groups HTTP/1.1\r\nHost: {}\r\nX-Token: {}\r\nContent-Length: {}\r\n\r\n{}",
+      method,
+      host,
+      token,
+      body.len(),
+      body
+    ).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response[9..12].parse().unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap().to_owned();
+
+    (status, body)
+  }
+
+  #[test]
+  /// Saved groups are validated, written to groups.toml and reloaded
+  fn saves_valid_groups() {
+    let dir = TempDir::new("dark-binding-editor").unwrap();
+    let config = ConfigFolder::new(dir.path().to_owned());
+    let (sender, _rx) = unbounded();
+
+    fs::create_dir_all(config.data_dir()).unwrap();
+    fs::write(config.data_dir().join("champions.json"), r#"{ "Jinx": 222, "KogMaw": 96 }"#).unwrap();
+
+    let editor = WebEditor::serve(config.clone(), sender).unwrap();
+    let token = editor.url().split("token=").nth(1).unwrap().to_owned();
+
+    assert_eq!(request(&editor, "GET", "wrong", "").0, 403);
+
+    let (status, body) = request(&editor, "PUT", &token, r#"{ "groups": { "adc": ["Jinx", "Jihn"] } }"#);
+    assert_eq!(status, 422);
+    assert!(body.contains("Jihn"));
+
+    let (status, _) = request(&editor, "PUT", &token, r#"{ "groups": { "adc": ["Jinx", "Kog'Maw"] } }"#);
+    assert_eq!(status, 200);
+    assert_eq!(config.groups().unwrap().groups["adc"], vec!["Jinx", "Kog'Maw"]);
+
+    let (status, body) = request(&editor, "GET", &token, "");
+    assert_eq!(status, 200);
+    assert!(body.contains("KogMaw"));
+  }
+
+  #[test]
+  /// Oversized bodies are refused before being read, and only with the token
+  fn refuses_large_bodies() {
+    let dir = TempDir::new("dark-binding-editor").unwrap();
+    let config = ConfigFolder::new(dir.path().to_owned());
+    let (sender, _rx) = unbounded();
+
+    let editor = WebEditor::serve(config, sender).unwrap();
+    let token = editor.url().split("token=").nth(1).unwrap().to_owned();
+    let host = editor.url()["http://".len()..].split('/').next().unwrap().to_owned();
+
+    let send_head = |token: &str| {
+      let mut stream = TcpStream::connect(&host[..]).unwrap();
+
+      write!(
+        stream,
+        "PUT /api/groups HTTP/1.1\r\nHost: {}\r\nX-Token: {}\r\nContent-Length: 1000000000\r\n\r\n",
+        host,
+        token
+      ).unwrap();
+
+      let mut response = String::new();
+      stream.read_to_string(&mut response).unwrap();
+
+      response[9..12].to_owned()
+    };
+
+    assert_eq!(send_head("wrong"), "403");
+    assert_eq!(send_head(&token), "413");
+  }
+}
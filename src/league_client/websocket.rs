@@ -3,10 +3,12 @@ use websocket::ws::dataframe::DataFrame;
 use serde::de::{Deserialize, Visitor, Deserializer, Error as DeserError};
 use std::result::Result as StdResult;
 use std::fmt;
+use std::time::Instant;
 use serde_json::{from_slice, from_value, Value};
 use regex::Regex;
 
 use league_client::LeagueClient;
+use league_client::live_client;
 use league_client::structs::*;
 use errors::*;
 
@@ -84,43 +86,167 @@ pub trait LeagueSocketHandler {
       }
     }
 
-    match event.uri.as_str() {
-      "/lol-champ-select/v1/session" => {
-        match event.event_type {
-          EventType::Update => {
-            self.handle_champ_select_v1_update(event.data);
-          }
-          _ => {}
-        }
-      }
-      _ => {}
+    let result = match event.uri.as_str() {
+      "/lol-champ-select/v1/session" => match event.event_type {
+        EventType::Create => self.handle_champ_select_v1_create(),
+        EventType::Update => self.handle_champ_select_v1_update(event.data),
+        EventType::Delete => self.handle_champ_select_v1_delete()
+      },
+      "/lol-gameflow/v1/session" => match event.event_type {
+        EventType::Update => self.handle_gameflow_v1_session_update(event.data),
+        _ => Ok(())
+      },
+      "/lol-summoner/v1/current-summoner" => match event.event_type {
+        EventType::Create | EventType::Update => self.handle_current_summoner_update(event.data),
+        _ => Ok(())
+      },
+      _ => Ok(())
     };
+
+    if let Err(e) = result {
+      self.on_error(&format!("handling {}", event.uri), e);
+    }
   }
 
+  /// Called with every error returned by the handlers
+  fn on_error(&mut self, operation: &str, e: Error);
+
+  fn handle_champ_select_v1_create(&mut self) -> Result<()>;
+
   fn handle_champ_select_v1_update(&mut self, data: Value) -> Result<()>;
+
+  fn handle_champ_select_v1_delete(&mut self) -> Result<()>;
+
+  fn handle_gameflow_v1_session_update(&mut self, data: Value) -> Result<()>;
+
+  fn handle_current_summoner_update(&mut self, data: Value) -> Result<()>;
+}
+
+impl LeagueClient {
+  /// Links the tracked champion's group if one is due
+  ///
+  /// The champion counts as applied even if the swap fails, the failure is
+  /// reported here only and the swap is attempted again once the champion
+  /// changes or a new champ select starts, not on every session update
+  fn apply_tracked_champion(&mut self, champion_id: Option<i32>) -> Result<()> {
+    if let Some(champion_id) = champion_id {
+      self.champion_tracker.applied(champion_id);
+
+      let result = self.load_champion_config(champion_id);
+      self.reporter().outcome("swapping bindings", result).ok();
+    }
+
+    Ok(())
+  }
+
+  /// Asks the running game which champion the local player is on
+  fn live_client_champion(&self) -> Option<i32> {
+    if !self.uses_live_client() {
+      return None;
+    }
+
+    match live_client::active_player_champion() {
+      Ok(Some(name)) => self.champion_id(&name),
+      Ok(None) => None,
+      Err(e) => {
+        debug!("live client data unavailable: {}", e);
+        None
+      }
+    }
+  }
 }
 
 impl LeagueSocketHandler for LeagueClient {
+  fn on_error(&mut self, operation: &str, e: Error) {
+    self.reporter().error(operation, &e);
+  }
+
+  fn handle_champ_select_v1_create(&mut self) -> Result<()> {
+    self.champion_tracker.reset();
+
+    Ok(())
+  }
+
   fn handle_champ_select_v1_update(&mut self, data: Value) -> Result<()> {
-    // debug!("{:?}", data);
     let update: ChampionSelectSessionUpdate = from_value(data)?;
 
-    if update.timer.phase == ChampionSelectTimerPhase::Finalization {
-      self
-        .local_summoner()
-        .map(|s| s.summoner_id)
-        .and_then(
-          |current_summoner_id| {
-            update
-              .my_team
-              .iter()
-              .find(|&m| m.summoner_id == current_summoner_id)
-              .map(|member| member.champion_id)
-          }
-        )
-        .map(|champion_id| self.load_champion_config(champion_id));
+    let summoner_id = match self.local_summoner() {
+      Some(s) => s.summoner_id,
+      None => return Ok(())
+    };
+
+    if let Some(champion_id) = update.hovered_champion(&summoner_id) {
+      debug!("hovering champion {}", champion_id);
     }
 
-    Ok(())
+    let now = Instant::now();
+
+    // Lock-in is detected from the session itself rather than the timer
+    // phase, blind pick, customs and practice tool never reach Finalization
+    if let Some(champion_id) = update.locked_champion(&summoner_id) {
+      debug!("locked in champion {} ({:?})", champion_id, update.timer.phase);
+
+      self.champion_tracker.observe(champion_id, now);
+    }
+
+    let due = self.champion_tracker.due(now);
+
+    self.apply_tracked_champion(due)
+  }
+
+  fn handle_champ_select_v1_delete(&mut self) -> Result<()> {
+    // Champ select is over, whatever is still debouncing is final
+    let pending = self.champion_tracker.pending();
+
+    self.apply_tracked_champion(pending)
+  }
+
+  fn handle_gameflow_v1_session_update(&mut self, data: Value) -> Result<()> {
+    let session: GameflowSession = from_value(data)?;
+
+    self.set_game_in_progress(match session.phase {
+      GameflowPhase::GameStart | GameflowPhase::InProgress | GameflowPhase::Reconnect => true,
+      _ => false
+    });
+
+    // Practice tool and some custom modes skip champ select entirely, the
+    // gameflow session still carries the champion before the game launches
+    match session.phase {
+      GameflowPhase::GameStart | GameflowPhase::InProgress | GameflowPhase::Reconnect => {}
+      _ => return Ok(())
+    }
+
+    let internal_name = match self.local_summoner() {
+      Some(s) => s.internal_name,
+      None => return Ok(())
+    };
+
+    let champion_id = match session.local_champion(&internal_name) {
+      Some(id) => Some(id),
+      None if session.phase != GameflowPhase::GameStart => self.live_client_champion(),
+      None => None
+    };
+
+    debug!("gameflow {:?} with champion {:?}", session.phase, champion_id);
+
+    if let Some(champion_id) = champion_id {
+      self.champion_tracker.observe(champion_id, Instant::now());
+    }
+
+    let pending = self.champion_tracker.pending();
+
+    self.apply_tracked_champion(pending)
+  }
+
+  fn handle_current_summoner_update(&mut self, data: Value) -> Result<()> {
+    let summoner: LocalSummoner = from_value(data)?;
+
+    if self.local_summoner().map_or(false, |s| s.summoner_id == summoner.summoner_id) {
+      return Ok(());
+    }
+
+    debug!("logged in as summoner {}", summoner.summoner_id);
+
+    self.account_changed()
   }
 }
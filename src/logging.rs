@@ -0,0 +1,209 @@
+//! Leveled logging to the console and to rotating files in `.dark-binding/logs`
+//!
+//! Lines are `key=value` pairs after the timestamp and level, so they can be
+//! grepped and parsed:
+//!
+//! ```text
+//! 2018-03-04T18:22:09Z INFO  dark_binding::league_client::config op=link_group group="adc" outcome=ok
+//! ```
+//!
+//! The console gets info and up, or debug too when `logging.verbose` is set or in
+//! debug builds. The log file always gets debug and up.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::{self, Level, LevelFilter, Log, Metadata, Record};
+
+use errors::*;
+
+/// Size a log file reaches before it's rotated
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Rotated files kept next to the current one
+const KEPT_FILES: usize = 4;
+
+static VERBOSE: AtomicBool = ATOMIC_BOOL_INIT;
+
+lazy_static! {
+  static ref LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+}
+
+struct Logger;
+
+impl Log for Logger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    metadata.level() <= Level::Debug
+  }
+
+  fn log(&self, record: &Record) {
+    // Dependencies are only interesting when something is wrong
+    if !record.target().starts_with("dark_binding") && record.level() > Level::Warn {
+      return;
+    }
+
+    let line = format!(
+      "{} {:<5} {} {}\n",
+      timestamp(SystemTime::now()),
+      record.level(),
+      record.target(),
+      record.args()
+    );
+
+    if record.level() <= Level::Info || verbose() {
+      print!("{}", line);
+    }
+
+    if let Some(ref mut file) = *LOG_FILE.lock().unwrap() {
+      file.write(line.as_bytes()).ok();
+    }
+  }
+
+  fn flush(&self) {
+    io::stdout().flush().ok();
+  }
+}
+
+/// Installs the logger, only the console is written to until `log_to` is called
+pub fn init() -> Result<()> {
+  log::set_boxed_logger(Box::new(Logger)).map_err(|e| format!("unable to set up logging: {}", e))?;
+  log::set_max_level(LevelFilter::Debug);
+
+  Ok(())
+}
+
+/// Also writes every line to rotating files in `dir`
+pub fn log_to(dir: &Path) -> Result<()> {
+  *LOG_FILE.lock().unwrap() = Some(RotatingFile::open(dir, "dark-binding.log", MAX_FILE_SIZE, KEPT_FILES)?);
+
+  Ok(())
+}
+
+/// Prints debug output to the console in release builds too
+pub fn set_verbose(verbose: bool) {
+  VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+fn verbose() -> bool {
+  cfg!(debug_assertions) || VERBOSE.load(Ordering::Relaxed)
+}
+
+/// A log file moved to `<name>.1`, `<name>.2` and so on once it grows past `max_size`
+struct RotatingFile {
+  path: PathBuf,
+  file: File,
+  size: u64,
+  max_size: u64,
+  kept: usize
+}
+
+impl RotatingFile {
+  fn open(dir: &Path, name: &str, max_size: u64, kept: usize) -> Result<RotatingFile> {
+    fs::create_dir_all(dir)?;
+
+    let path = dir.join(name);
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let size = file.metadata()?.len();
+
+    Ok(RotatingFile {
+      path: path,
+      file: file,
+      size: size,
+      max_size: max_size,
+      kept: kept
+    })
+  }
+
+  fn write(&mut self, line: &[u8]) -> Result<()> {
+    if self.size + line.len() as u64 > self.max_size && self.size > 0 {
+      self.rotate()?;
+    }
+
+    self.file.write_all(line)?;
+    self.size += line.len() as u64;
+
+    Ok(())
+  }
+
+  fn rotate(&mut self) -> Result<()> {
+    let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+
+    fs::remove_file(rotated(self.kept)).ok();
+
+    for n in (1..self.kept).rev() {
+      fs::rename(rotated(n), rotated(n + 1)).ok();
+    }
+
+    fs::rename(&self.path, rotated(1))?;
+
+    self.file = File::create(&self.path)?;
+    self.size = 0;
+
+    Ok(())
+  }
+}
+
+/// UTC time as `YYYY-MM-DDTHH:MM:SSZ`
+fn timestamp(time: SystemTime) -> String {
+  let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+  let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+  // Howard Hinnant's days to civil date conversion
+  let z = days + 719_468;
+  let era = z / 146_097;
+  let day_of_era = z - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let mp = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  format!(
+    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+    year,
+    month,
+    day,
+    secs_of_day / 3600,
+    secs_of_day / 60 % 60,
+    secs_of_day % 60
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+  use tempdir::TempDir;
+
+  use super::*;
+
+  #[test]
+  /// Full files move up one number and the oldest is dropped
+  fn rotates_files() {
+    let dir = TempDir::new("dark-binding-logs").unwrap();
+    let mut file = RotatingFile::open(dir.path(), "test.log", 10, 2).unwrap();
+
+    for line in &["first\n", "second\n", "third\n", "fourth\n"] {
+      file.write(line.as_bytes()).unwrap();
+    }
+
+    let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+
+    assert_eq!(read("test.log"), "fourth\n");
+    assert_eq!(read("test.log.1"), "third\n");
+    assert_eq!(read("test.log.2"), "second\n");
+    assert!(!dir.path().join("test.log.3").exists());
+  }
+
+  #[test]
+  /// Timestamps are UTC dates
+  fn formats_timestamps() {
+    assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    assert_eq!(
+      timestamp(UNIX_EPOCH + Duration::from_secs(1_520_187_729)),
+      "2018-03-04T18:22:09Z"
+    );
+  }
+}
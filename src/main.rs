@@ -2,7 +2,6 @@
 #![recursion_limit = "2048"]
 // Make rustc's built-in lints more strict (I'll opt back out selectively)
 #![warn(warnings)]
-#![allow(unused_must_use)]
 // (Or at least find a way to enable build-time and `cargo clippy`-time with a single feature)
 // Set clippy into a whitelist-based configuration so I'll see new lints as they come in
 #![cfg_attr(feature = "cargo-clippy", warn(clippy_pedantic, clippy_restrictions))]
@@ -13,34 +12,53 @@
 #[cfg(feature = "nightly")]
 extern crate alloc_system;
 
+extern crate base64;
 #[macro_use]
 extern crate clap;
+extern crate ctrlc;
 #[macro_use]
 extern crate error_chain;
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate log;
 extern crate native_tls;
 extern crate rand;
 extern crate regex;
 extern crate reqwest;
+extern crate ring;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
+extern crate sha2;
+#[cfg(feature = "tray")]
 extern crate systray;
+#[cfg(test)]
+extern crate tempdir;
 extern crate tokio_core;
 extern crate toml;
+extern crate untrusted;
 extern crate websocket;
+#[cfg(windows)]
+extern crate winapi;
 
+mod autostart;
 mod errors;
-#[macro_use]
-mod util;
+mod commands;
 mod league_client;
+mod logging;
+mod trust;
+mod updater;
 
-use clap::{App, Arg};
+use std::path::{Path, PathBuf};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use reqwest::{Certificate, Client};
 
 use errors::*;
+use league_client::{DirectoryOverrides, Locations};
+use trust::TrustConfig;
 
 static CERTIFICATE: &'static [u8] = include_bytes!("../lcu.der");
 
@@ -73,31 +91,172 @@ fn main() {
 }
 
 fn run() -> Result<()> {
-  let _matches = App::new(env!("CARGO_PKG_NAME"))
+  logging::init()?;
+
+  let matches = App::new(env!("CARGO_PKG_NAME"))
     .version(crate_version!())
     .arg(
       Arg::with_name("no-check-update")
-        .help("Disable auto update")
+        .help("Don't check for updates")
         .long("no-check-update")
         .takes_value(false)
+        .global(true)
+    )
+    .arg(
+      Arg::with_name("record")
+        .help("Record every websocket frame received to a JSONL file")
+        .long("record")
+        .value_name("FILE")
+        .takes_value(true)
+        .conflicts_with("replay")
+    )
+    .arg(
+      Arg::with_name("replay")
+        .help("Replay a recording against a temporary copy of the config directory")
+        .long("replay")
+        .value_name("FILE")
+        .takes_value(true)
+    )
+    .arg(
+      Arg::with_name("replay-speed")
+        .help("Replay speed multiplier, 0 replays without delays")
+        .long("replay-speed")
+        .value_name("SPEED")
+        .takes_value(true)
+        .default_value("1")
+    )
+    .arg(
+      Arg::with_name("dry-run")
+        .help("Print the config swaps that would happen without touching any file")
+        .long("dry-run")
+        .takes_value(false)
+        .global(true)
+    )
+    .arg(
+      Arg::with_name("lcu-root-cert")
+        .help("PEM or DER root certificate to trust for the League client instead of the bundled one")
+        .long("lcu-root-cert")
+        .value_name("FILE")
+        .takes_value(true)
+    )
+    .arg(
+      Arg::with_name("lcu-cert-fingerprint")
+        .help("Expected SHA-256 fingerprint of a certificate the League client presents")
+        .long("lcu-cert-fingerprint")
+        .value_name("SHA256")
+        .takes_value(true)
+    )
+    .arg(
+      Arg::with_name("headless")
+        .help("Run without the system tray, quit with Ctrl-C or through the control socket")
+        .long("headless")
+        .takes_value(false)
+        .global(true)
+    )
+    .arg(
+      Arg::with_name("config-dir")
+        .help("League of Legends Config directory")
+        .long("config-dir")
+        .value_name("DIR")
+        .takes_value(true)
+        .global(true)
     )
+    .arg(
+      Arg::with_name("install-dir")
+        .help("League of Legends install directory, its Config folder is used")
+        .long("install-dir")
+        .value_name("DIR")
+        .takes_value(true)
+        .global(true)
+    )
+    .arg(
+      Arg::with_name("installation")
+        .help("Name of an installation from locations.toml")
+        .long("installation")
+        .value_name("NAME")
+        .takes_value(true)
+        .global(true)
+    )
+    .subcommands(commands::subcommands())
+    .subcommand(SubCommand::with_name("install").about("Start when you log in, with the options given"))
+    .subcommand(SubCommand::with_name("uninstall").about("Stop starting when you log in"))
     .get_matches();
 
-  // if matches.is_present("no-check-update") {
-  //   // do stuff
-  // };
+  match matches.subcommand() {
+    ("install", Some(sub_matches)) => return autostart::install(&launch_args(&matches, sub_matches)),
+    ("uninstall", _) => return autostart::uninstall(),
+    _ => {}
+  }
+
+  if let (name, Some(sub_matches)) = matches.subcommand() {
+    let config_dir = directory_overrides(&matches, Some(sub_matches)).resolve(&Locations::load()?, None)?;
+    let dry_run = sub_matches.is_present("dry-run") || matches.is_present("dry-run");
+
+    return commands::run(name, sub_matches, config_dir, dry_run);
+  }
+
+  if let Some(recording) = matches.value_of("replay") {
+    let config_dir = directory_overrides(&matches, None).resolve(&Locations::load()?, None)?;
+    let speed: f64 = value_t!(matches, "replay-speed", f64).unwrap_or_else(|e| e.exit());
+
+    let result_dir = league_client::replay(Path::new(recording), &config_dir, speed, matches.is_present("dry-run"))?;
+    println!("replay finished, resulting config directory: {}", result_dir.display());
+
+    return Ok(());
+  }
 
-  league_client::run();
+  let options = league_client::Options {
+    record: matches.value_of("record").map(PathBuf::from),
+    dry_run: matches.is_present("dry-run"),
+    trust: TrustConfig {
+      root_certificate: matches.value_of("lcu-root-cert").map(PathBuf::from),
+      fingerprint: matches.value_of("lcu-cert-fingerprint").map(str::to_owned)
+    },
+    headless: matches.is_present("headless"),
+    check_updates: !matches.is_present("no-check-update"),
+    directories: directory_overrides(&matches, None)
+  };
 
-  Ok(())
+  league_client::run(&options)
 }
 
-#[cfg(test)]
-mod tests {
+/// Options of `install` kept for the launches at login
+fn launch_args(matches: &ArgMatches, sub_matches: &ArgMatches) -> Vec<String> {
+  let mut args = Vec::new();
 
-  #[test]
-  /// Test something
-  fn test_something() {
-    unimplemented!();
+  for flag in &["headless", "no-check-update"] {
+    if matches.is_present(flag) || sub_matches.is_present(flag) {
+      args.push(format!("--{}", flag));
+    }
   }
+
+  for option in &["config-dir", "install-dir", "installation", "lcu-root-cert", "lcu-cert-fingerprint"] {
+    if let Some(value) = sub_matches.value_of(option).or_else(|| matches.value_of(option)) {
+      // Logins don't start in the current directory
+      let value = match *option {
+        "installation" | "lcu-cert-fingerprint" => value.to_owned(),
+        _ => Path::new(value).canonicalize().map(|p| p.display().to_string()).unwrap_or_else(|_| value.to_owned())
+      };
+
+      args.push(format!("--{}", option));
+      args.push(value);
+    }
+  }
+
+  args
+}
+
+/// Directory overrides given either before or after the subcommand, then from the environment
+fn directory_overrides(matches: &ArgMatches, sub_matches: Option<&ArgMatches>) -> DirectoryOverrides {
+  let value_of = |name: &str| {
+    sub_matches
+      .and_then(|m| m.value_of(name))
+      .or_else(|| matches.value_of(name))
+  };
+
+  DirectoryOverrides {
+    config_dir: value_of("config-dir").map(PathBuf::from),
+    install_dir: value_of("install-dir").map(PathBuf::from),
+    installation: value_of("installation").map(str::to_owned)
+  }.with_env()
 }
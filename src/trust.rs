@@ -0,0 +1,214 @@
+use std::fs::File;
+use std::io::Read;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use base64;
+use native_tls::{self, TlsConnector};
+use reqwest::{self, Client};
+use sha2::{Digest, Sha256};
+
+use CERTIFICATE;
+use errors::*;
+
+/// How the LCU certificate is trusted, from the command line or the
+/// `[certificate]` section of settings.toml
+///
+/// The bundled `lcu.der` is used unless a root certificate file is given, and
+/// a SHA-256 fingerprint can be pinned so a rotated or tampered certificate is
+/// rejected instead of silently trusted
+#[serde(default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrustConfig {
+  /// PEM or DER encoded root certificate replacing the bundled one
+  pub root_certificate: Option<PathBuf>,
+  /// Expected SHA-256 fingerprint of a certificate the LCU presents, hex with
+  /// or without colons
+  pub fingerprint: Option<String>
+}
+
+impl TrustConfig {
+  /// Each setting of `self`, or of `fallback` when left out
+  pub fn or(&self, fallback: &TrustConfig) -> TrustConfig {
+    TrustConfig {
+      root_certificate: self.root_certificate.clone().or_else(|| fallback.root_certificate.clone()),
+      fingerprint: self.fingerprint.clone().or_else(|| fallback.fingerprint.clone())
+    }
+  }
+}
+
+/// LCU root certificate, shared by the REST and websocket clients, and the
+/// pinned fingerprint the LCU's certificates are checked against
+#[derive(Debug, Clone)]
+pub struct Trust {
+  root_der: Vec<u8>,
+  fingerprint: Option<String>
+}
+
+impl Trust {
+  pub fn load(config: &TrustConfig) -> Result<Trust> {
+    let root_der = match config.root_certificate {
+      Some(ref path) => {
+        let mut contents = Vec::new();
+        File::open(path)
+          .and_then(|mut f| f.read_to_end(&mut contents))
+          .chain_err(|| format!("unable to read LCU root certificate {:?}", path))?;
+
+        decode_certificate(&contents)?
+      }
+      None => CERTIFICATE.to_vec()
+    };
+
+    Ok(Trust {
+      root_der: root_der,
+      fingerprint: config.fingerprint.as_ref().map(|f| normalize_fingerprint(f))
+    })
+  }
+
+  pub fn from_der(der: &[u8]) -> Trust {
+    Trust {
+      root_der: der.to_vec(),
+      fingerprint: None
+    }
+  }
+
+  pub fn root_der(&self) -> &[u8] {
+    &self.root_der
+  }
+
+  pub fn http_client(&self) -> Result<Client> {
+    Ok(
+      Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_der(&self.root_der)?)
+        .build()?
+    )
+  }
+
+  pub fn tls_connector(&self) -> Result<TlsConnector> {
+    let certificate = native_tls::Certificate::from_der(&self.root_der)
+      .map_err(|e| ErrorKind::CertificateVerification(e.to_string()))?;
+
+    let mut builder = TlsConnector::builder().map_err(|e| ErrorKind::CertificateVerification(e.to_string()))?;
+    // The LCU listens on 127.0.0.1 with a certificate issued to `rclient`, which
+    // is the name verified instead of the address
+    builder
+      .add_root_certificate(certificate)
+      .and_then(|b| b.sni_name("rclient"))
+      .map_err(|e| ErrorKind::CertificateVerification(e.to_string()))?;
+
+    Ok(builder.build().map_err(|e| ErrorKind::CertificateVerification(e.to_string()))?)
+  }
+
+  /// Performs a TLS handshake with the LCU on `port` so a certificate the
+  /// root doesn't cover is reported up front instead of as a failed request,
+  /// and checks the pinned fingerprint against the certificates it presents
+  pub fn verify(&self, port: &str) -> Result<()> {
+    let port: u16 = port.parse()?;
+    let stream = TcpStream::connect(("127.0.0.1", port))?;
+
+    let stream = self
+      .tls_connector()?
+      .connect("127.0.0.1", stream)
+      .map_err(|e| ErrorKind::CertificateVerification(e.to_string()))?;
+
+    let expected = match self.fingerprint {
+      Some(ref expected) => expected,
+      None => return Ok(())
+    };
+
+    let chain = stream
+      .peer_certificate_chain()
+      .map_err(|e| ErrorKind::CertificateVerification(e.to_string()))?;
+    let presented: Vec<String> = chain.iter().map(|der| fingerprint(der)).collect();
+
+    if !presented.contains(expected) {
+      bail!(ErrorKind::CertificateVerification(format!(
+        "presented certificate fingerprints are {}, expected {}",
+        presented.join(", "),
+        expected
+      )));
+    }
+
+    Ok(())
+  }
+}
+
+/// Lowercase hex SHA-256 of a DER encoded certificate
+pub fn fingerprint(der: &[u8]) -> String {
+  Sha256::digest(der)
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect()
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+  fingerprint
+    .chars()
+    .filter(|c| c.is_digit(16))
+    .collect::<String>()
+    .to_lowercase()
+}
+
+/// Accepts DER as is and decodes the first certificate of a PEM file
+fn decode_certificate(contents: &[u8]) -> Result<Vec<u8>> {
+  let text = String::from_utf8_lossy(contents);
+
+  if !text.contains("-----BEGIN CERTIFICATE-----") {
+    return Ok(contents.to_vec());
+  }
+
+  let body: String = text
+    .lines()
+    .skip_while(|l| !l.starts_with("-----BEGIN CERTIFICATE-----"))
+    .skip(1)
+    .take_while(|l| !l.starts_with("-----END CERTIFICATE-----"))
+    .map(|l| l.trim())
+    .collect();
+
+  base64::decode(&body).chain_err(|| "invalid PEM certificate")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  /// Fingerprints compare regardless of colons, spaces and case
+  fn normalizes_fingerprints() {
+    assert_eq!(normalize_fingerprint("AB:cd:01 EF"), "abcd01ef");
+    assert_eq!(normalize_fingerprint(&fingerprint(CERTIFICATE)), fingerprint(CERTIFICATE));
+  }
+
+  #[test]
+  /// DER is used as is, PEM is decoded to the same DER
+  fn decodes_pem_and_der() {
+    let pem = format!(
+      "subject=rclient\n-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+      base64::encode(CERTIFICATE)
+    );
+
+    assert_eq!(decode_certificate(CERTIFICATE).unwrap(), CERTIFICATE);
+    assert_eq!(decode_certificate(pem.as_bytes()).unwrap(), CERTIFICATE);
+    assert!(decode_certificate(b"-----BEGIN CERTIFICATE-----\n!!\n-----END CERTIFICATE-----").is_err());
+  }
+
+  #[test]
+  /// Command line options win over settings.toml, setting by setting
+  fn prefers_command_line() {
+    let cli = TrustConfig {
+      root_certificate: None,
+      fingerprint: Some("ab".to_owned())
+    };
+    let settings = TrustConfig {
+      root_certificate: Some(PathBuf::from("lcu.pem")),
+      fingerprint: Some("cd".to_owned())
+    };
+
+    assert_eq!(
+      cli.or(&settings),
+      TrustConfig {
+        root_certificate: Some(PathBuf::from("lcu.pem")),
+        fingerprint: Some("ab".to_owned())
+      }
+    );
+  }
+}
@@ -0,0 +1,359 @@
+//! Checks for, downloads and installs new releases
+//!
+//! A release is described by a JSON manifest:
+//!
+//! ```json
+//! { "version": "0.2.0", "url": "dark-binding-0.2.0.exe", "sha256": "…", "signature": "…" }
+//! ```
+//!
+//! `url` may be relative to the manifest and `version` is dotted numbers only.
+//! The artifact must match `sha256` (hex), and `signature` is a base64 Ed25519
+//! signature of `<version>\n<sha256>` made with the release key whose public
+//! half is built in through `DARK_BINDING_UPDATE_KEY`. Signing the version
+//! along with the digest keeps an older release from being relabelled as a
+//! newer one. Builds without a key never update.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use base64;
+use reqwest::Url;
+use ring::signature;
+use sha2::{Digest, Sha256};
+use untrusted;
+
+use HTTP_CLIENT;
+use errors::*;
+
+/// Release manifest read from the manifest URL
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Manifest {
+  pub version: String,
+  pub url: String,
+  pub sha256: String,
+  pub signature: String
+}
+
+impl Manifest {
+  /// What the release key signs
+  pub fn signed_message(&self) -> String {
+    format!("{}\n{}", self.version, self.sha256.to_lowercase())
+  }
+}
+
+/// A newer release, downloaded and verified
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+  pub version: String,
+  pub path: PathBuf
+}
+
+pub struct UpdateChecker {
+  manifest_url: String,
+  public_key: Vec<u8>,
+  download_dir: PathBuf
+}
+
+impl UpdateChecker {
+  pub fn new(manifest_url: &str, public_key: &[u8], download_dir: &Path) -> UpdateChecker {
+    UpdateChecker {
+      manifest_url: manifest_url.to_owned(),
+      public_key: public_key.to_vec(),
+      download_dir: download_dir.to_owned()
+    }
+  }
+
+  /// Checker using the release key built in, `None` for builds without one or
+  /// without any manifest URL
+  pub fn from_build(manifest_url: Option<&str>, download_dir: &Path) -> Result<Option<UpdateChecker>> {
+    let manifest_url = match manifest_url.or(option_env!("DARK_BINDING_UPDATE_URL")) {
+      Some(url) => url,
+      None => return Ok(None)
+    };
+
+    let public_key = match option_env!("DARK_BINDING_UPDATE_KEY") {
+      Some(key) => base64::decode(key).chain_err(|| "invalid built in update key")?,
+      None => return Ok(None)
+    };
+
+    Ok(Some(UpdateChecker::new(manifest_url, &public_key, download_dir)))
+  }
+
+  /// Downloads and verifies the release newer than `current_version`, if there is one
+  pub fn check(&self, current_version: &str) -> Result<Option<Update>> {
+    let manifest: Manifest = HTTP_CLIENT
+      .get(&self.manifest_url)
+      .send()?
+      .error_for_status()?
+      .json()
+      .chain_err(|| ErrorKind::Parse(format!("update manifest {}", self.manifest_url)))?;
+
+    debug!("op=check_update current={} available={}", current_version, manifest.version);
+
+    if !is_newer(&manifest.version, current_version) {
+      return Ok(None);
+    }
+
+    let path = download_path(&self.download_dir, &manifest.version)?;
+
+    if path.exists() && verify(&manifest, &read(&path)?, &self.public_key).is_ok() {
+      return Ok(Some(Update {
+        version: manifest.version,
+        path: path
+      }));
+    }
+
+    let url = Url::parse(&self.manifest_url)?.join(&manifest.url)?;
+    let mut artifact = Vec::new();
+
+    HTTP_CLIENT
+      .get(url)
+      .send()?
+      .error_for_status()?
+      .read_to_end(&mut artifact)?;
+
+    verify(&manifest, &artifact, &self.public_key)?;
+
+    fs::create_dir_all(path.parent().unwrap())?;
+    File::create(&path)?.write_all(&artifact)?;
+
+    info!("op=download_update version={} path={:?} outcome=ok", manifest.version, path);
+
+    Ok(Some(Update {
+      version: manifest.version,
+      path: path
+    }))
+  }
+}
+
+/// Replaces the running executable with the update, the new version starts
+/// with the next launch
+///
+/// The running executable can't be overwritten on Windows, so it's moved aside
+/// to `<name>.old` first and removed on a later install.
+pub fn install(update: &Update) -> Result<PathBuf> {
+  let current = env::current_exe()?;
+  let old = current.with_extension("old");
+
+  if old.exists() {
+    fs::remove_file(&old)?;
+  }
+
+  fs::rename(&current, &old).chain_err(|| ErrorKind::ConfigIo("move aside".to_owned(), current.clone()))?;
+
+  if let Err(e) = fs::copy(&update.path, &current) {
+    fs::rename(&old, &current).ok();
+
+    return Err(Error::with_chain(e, ErrorKind::ConfigIo("install".to_owned(), current)));
+  }
+
+  set_executable(&current)?;
+
+  info!("op=install_update version={} path={:?} outcome=ok", update.version, current);
+
+  Ok(current)
+}
+
+fn verify(manifest: &Manifest, artifact: &[u8], public_key: &[u8]) -> Result<()> {
+  let digest: String = Sha256::digest(artifact)
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect();
+
+  if digest != manifest.sha256.to_lowercase() {
+    bail!(ErrorKind::UpdateVerification(format!("expected sha256 {}, got {}", manifest.sha256, digest)));
+  }
+
+  let signature_bytes = base64::decode(&manifest.signature)
+    .map_err(|_| ErrorKind::UpdateVerification("the signature isn't base64".to_owned()))?;
+
+  signature::verify(
+    &signature::ED25519,
+    untrusted::Input::from(public_key),
+    untrusted::Input::from(manifest.signed_message().as_bytes()),
+    untrusted::Input::from(&signature_bytes)
+  ).map_err(|_| ErrorKind::UpdateVerification("bad signature".to_owned()))?;
+
+  Ok(())
+}
+
+/// Compares dotted versions number by number, `0.10.0` is newer than `0.9.1`
+fn is_newer(candidate: &str, current: &str) -> bool {
+  let parse = |v: &str| -> Vec<u64> {
+    v.trim_left_matches('v')
+      .split(|c| c == '.' || c == '-')
+      .map(|part| part.parse().unwrap_or(0))
+      .collect()
+  };
+
+  parse(candidate) > parse(current)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+  let mut contents = Vec::new();
+  File::open(path)?.read_to_end(&mut contents)?;
+
+  Ok(contents)
+}
+
+/// Only dotted numbers, `version` ends up in a path
+fn is_valid_version(version: &str) -> bool {
+  version
+    .split('.')
+    .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Where the verified artifact of `version` is kept in `download_dir`
+pub fn download_path(download_dir: &Path, version: &str) -> Result<PathBuf> {
+  if !is_valid_version(version) {
+    bail!(ErrorKind::UpdateVerification(format!("invalid version {:?}", version)));
+  }
+
+  let name = if cfg!(windows) { "dark-binding.exe" } else { "dark-binding" };
+
+  Ok(download_dir.join(version).join(name))
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+  use std::os::unix::fs::PermissionsExt;
+
+  Ok(fs::set_permissions(path, fs::Permissions::from_mode(0o755))?)
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{BufRead, BufReader};
+  use std::net::TcpListener;
+  use std::thread;
+  use ring::rand::SystemRandom;
+  use ring::signature::Ed25519KeyPair;
+  use serde_json;
+  use tempdir::TempDir;
+
+  use super::*;
+
+  /// Serves `manifest` at `/manifest.json` and `artifact` everywhere else, returns the manifest URL
+  fn serve(manifest: Manifest, artifact: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/manifest.json", listener.local_addr().unwrap());
+
+    thread::spawn(move || for stream in listener.incoming() {
+      let mut stream = stream.unwrap();
+      let mut request = String::new();
+
+      {
+        let mut reader = BufReader::new(&mut stream);
+        let mut line = String::new();
+        reader.read_line(&mut request).unwrap();
+
+        // Headers are read off so closing the connection doesn't reset it
+        while reader.read_line(&mut line).unwrap() > 2 {
+          line.clear();
+        }
+      }
+
+      let body = if request.contains("/manifest.json") {
+        serde_json::to_vec(&manifest).unwrap()
+      } else {
+        artifact.clone()
+      };
+
+      write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).unwrap();
+      stream.write_all(&body).unwrap();
+    });
+
+    url
+  }
+
+  fn key_pair() -> Ed25519KeyPair {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+
+    Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8)).unwrap()
+  }
+
+  fn manifest(key_pair: &Ed25519KeyPair, artifact: &[u8]) -> Manifest {
+    let mut manifest = Manifest {
+      version: "0.2.0".to_owned(),
+      url: "dark-binding-0.2.0".to_owned(),
+      sha256: Sha256::digest(artifact).iter().map(|b| format!("{:02x}", b)).collect(),
+      signature: String::new()
+    };
+
+    manifest.signature = base64::encode(key_pair.sign(manifest.signed_message().as_bytes()).as_ref());
+    manifest
+  }
+
+  fn assert_rejected(checker: &UpdateChecker) {
+    match checker.check("0.1.0") {
+      Err(Error(ErrorKind::UpdateVerification(_), _)) => {}
+      other => panic!("expected a verification error, got {:?}", other)
+    }
+  }
+
+  #[test]
+  /// Newer signed releases are downloaded, older ones are ignored
+  fn downloads_signed_update() {
+    let dir = TempDir::new("dark-binding-update").unwrap();
+    let key_pair = key_pair();
+    let artifact = b"new release".to_vec();
+    let url = serve(manifest(&key_pair, &artifact), artifact.clone());
+    let checker = UpdateChecker::new(&url, key_pair.public_key_bytes(), dir.path());
+
+    let update = checker.check("0.1.0").unwrap().unwrap();
+
+    assert_eq!(update.version, "0.2.0");
+    assert_eq!(read(&update.path).unwrap(), artifact);
+    assert_eq!(checker.check("0.2.0").unwrap(), None);
+  }
+
+  #[test]
+  /// Artifacts that don't match the manifest's signature are rejected
+  fn rejects_tampered_update() {
+    let dir = TempDir::new("dark-binding-update").unwrap();
+    let key_pair = key_pair();
+    let mut manifest = manifest(&key_pair, b"new release");
+    let tampered = b"evil release".to_vec();
+
+    manifest.sha256 = Sha256::digest(&tampered).iter().map(|b| format!("{:02x}", b)).collect();
+
+    let url = serve(manifest, tampered);
+    let checker = UpdateChecker::new(&url, key_pair.public_key_bytes(), dir.path());
+
+    assert_rejected(&checker);
+  }
+
+  #[test]
+  /// A signed release can't be passed off as another version
+  fn rejects_relabelled_update() {
+    let dir = TempDir::new("dark-binding-update").unwrap();
+    let key_pair = key_pair();
+    let artifact = b"old release".to_vec();
+    let mut manifest = manifest(&key_pair, &artifact);
+
+    manifest.version = "9.0.0".to_owned();
+
+    let url = serve(manifest, artifact);
+    let checker = UpdateChecker::new(&url, key_pair.public_key_bytes(), dir.path());
+
+    assert_rejected(&checker);
+  }
+
+  #[test]
+  /// Versions are dotted numbers, anything else could point outside the download folder
+  fn validates_versions() {
+    let dir = Path::new("updates");
+
+    assert!(download_path(dir, "0.10.2").unwrap().starts_with("updates/0.10.2"));
+
+    for version in &["", "1.", "v1.0", "9.0/../../x", "..", "1.0-beta"] {
+      assert!(download_path(dir, version).is_err(), "{:?} was accepted", version);
+    }
+  }
+}